@@ -0,0 +1,183 @@
+#![no_std]
+
+//! SEP-41 loyalty points token.
+//!
+//! A platform-wide points token that utility contracts mint as a
+//! `Receipt::Points` reward, so partners can run loyalty programs without
+//! deploying a token of their own. The admin appoints minters; holders use
+//! the standard token interface to transfer, approve and burn points.
+//! Metadata and events follow `soroban-token-sdk`.
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, panic_with_error, token::TokenInterface, Address, Env,
+    String,
+};
+use soroban_token_sdk::{metadata::TokenMetadata, TokenUtils};
+
+pub mod storage;
+
+mod test;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum PointsError {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    NotAuthorized = 3,
+    NegativeAmount = 4,
+    InsufficientBalance = 5,
+    InsufficientAllowance = 6,
+    InvalidExpiration = 7,
+    InvalidDecimal = 8,
+    ArithmeticOverflow = 9,
+}
+
+/// Largest supported number of decimals.
+pub const MAX_DECIMAL: u32 = 18;
+
+fn require_non_negative(env: &Env, amount: i128) {
+    if amount < 0 {
+        panic_with_error!(env, PointsError::NegativeAmount)
+    }
+}
+
+#[contract]
+pub struct PointsToken;
+
+#[contractimpl]
+impl PointsToken {
+    pub fn initialize(env: Env, admin: Address, decimal: u32, name: String, symbol: String) {
+        if storage::has_admin(&env) {
+            panic_with_error!(&env, PointsError::AlreadyInitialized)
+        }
+        if decimal > MAX_DECIMAL {
+            panic_with_error!(&env, PointsError::InvalidDecimal)
+        }
+
+        storage::write_admin(&env, &admin);
+        TokenUtils::new(&env)
+            .metadata()
+            .set_metadata(&TokenMetadata {
+                decimal,
+                name,
+                symbol,
+            });
+    }
+
+    pub fn admin(env: Env) -> Address {
+        storage::read_admin(&env)
+    }
+
+    pub fn set_admin(env: Env, new_admin: Address) {
+        let admin = storage::read_admin(&env);
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        storage::write_admin(&env, &new_admin);
+        TokenUtils::new(&env).events().set_admin(admin, new_admin);
+    }
+
+    /// Allows or stops `minter`, typically a utility contract, minting
+    /// points.
+    pub fn set_minter(env: Env, minter: Address, allowed: bool) {
+        storage::read_admin(&env).require_auth();
+        storage::extend_instance(&env);
+
+        storage::write_minter(&env, &minter, allowed);
+    }
+
+    pub fn is_minter(env: Env, minter: Address) -> bool {
+        storage::is_minter(&env, &minter)
+    }
+
+    /// Mints `amount` points to `to`. `minter` must be the admin or an
+    /// appointed minter.
+    pub fn mint(env: Env, minter: Address, to: Address, amount: i128) {
+        require_non_negative(&env, amount);
+        minter.require_auth();
+        if minter != storage::read_admin(&env) && !storage::is_minter(&env, &minter) {
+            panic_with_error!(&env, PointsError::NotAuthorized)
+        }
+        storage::extend_instance(&env);
+
+        storage::receive_balance(&env, &to, amount);
+        TokenUtils::new(&env).events().mint(minter, to, amount);
+    }
+}
+
+#[contractimpl]
+impl TokenInterface for PointsToken {
+    fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        storage::extend_instance(&env);
+        storage::read_allowance(&env, &from, &spender).amount
+    }
+
+    fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        require_non_negative(&env, amount);
+        storage::extend_instance(&env);
+
+        storage::write_allowance(&env, &from, &spender, amount, expiration_ledger);
+        TokenUtils::new(&env)
+            .events()
+            .approve(from, spender, amount, expiration_ledger);
+    }
+
+    fn balance(env: Env, id: Address) -> i128 {
+        storage::extend_instance(&env);
+        storage::read_balance(&env, &id)
+    }
+
+    fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        require_non_negative(&env, amount);
+        storage::extend_instance(&env);
+
+        storage::spend_balance(&env, &from, amount);
+        storage::receive_balance(&env, &to, amount);
+        TokenUtils::new(&env).events().transfer(from, to, amount);
+    }
+
+    fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        require_non_negative(&env, amount);
+        storage::extend_instance(&env);
+
+        storage::spend_allowance(&env, &from, &spender, amount);
+        storage::spend_balance(&env, &from, amount);
+        storage::receive_balance(&env, &to, amount);
+        TokenUtils::new(&env).events().transfer(from, to, amount);
+    }
+
+    fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+        require_non_negative(&env, amount);
+        storage::extend_instance(&env);
+
+        storage::spend_balance(&env, &from, amount);
+        TokenUtils::new(&env).events().burn(from, amount);
+    }
+
+    fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        require_non_negative(&env, amount);
+        storage::extend_instance(&env);
+
+        storage::spend_allowance(&env, &from, &spender, amount);
+        storage::spend_balance(&env, &from, amount);
+        TokenUtils::new(&env).events().burn(from, amount);
+    }
+
+    fn decimals(env: Env) -> u32 {
+        TokenUtils::new(&env).metadata().get_metadata().decimal
+    }
+
+    fn name(env: Env) -> String {
+        TokenUtils::new(&env).metadata().get_metadata().name
+    }
+
+    fn symbol(env: Env) -> String {
+        TokenUtils::new(&env).metadata().get_metadata().symbol
+    }
+}
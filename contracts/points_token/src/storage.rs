@@ -0,0 +1,159 @@
+//! Balance, allowance and role storage for the points token.
+//!
+//! Admin and minters live in instance storage; balances and allowances are
+//! persistent and temporary entries keyed by `(symbol, address...)`.
+
+use soroban_sdk::{contracttype, panic_with_error, symbol_short, Address, Env, Symbol};
+
+use crate::PointsError;
+
+pub const ADMIN: Symbol = symbol_short!("ADMIN");
+pub const MINTER: Symbol = symbol_short!("MINTER");
+pub const BALANCE: Symbol = symbol_short!("BALANCE");
+pub const ALLOWANCE: Symbol = symbol_short!("ALLOW");
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+pub const INSTANCE_EXTEND_TO: u32 = 7 * DAY_IN_LEDGERS;
+pub const INSTANCE_THRESHOLD: u32 = INSTANCE_EXTEND_TO - DAY_IN_LEDGERS;
+pub const BALANCE_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+pub const BALANCE_THRESHOLD: u32 = BALANCE_EXTEND_TO - DAY_IN_LEDGERS;
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+pub fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_THRESHOLD, INSTANCE_EXTEND_TO);
+}
+
+pub fn has_admin(env: &Env) -> bool {
+    env.storage().instance().has(&ADMIN)
+}
+
+pub fn read_admin(env: &Env) -> Address {
+    env.storage()
+        .instance()
+        .get(&ADMIN)
+        .unwrap_or_else(|| panic_with_error!(env, PointsError::NotInitialized))
+}
+
+pub fn write_admin(env: &Env, admin: &Address) {
+    env.storage().instance().set(&ADMIN, admin);
+}
+
+pub fn is_minter(env: &Env, minter: &Address) -> bool {
+    env.storage()
+        .instance()
+        .get::<(Symbol, Address), bool>(&(MINTER, minter.clone()))
+        .unwrap_or(false)
+}
+
+pub fn write_minter(env: &Env, minter: &Address, allowed: bool) {
+    let key = (MINTER, minter.clone());
+    if allowed {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+}
+
+pub fn read_balance(env: &Env, id: &Address) -> i128 {
+    let key = (BALANCE, id.clone());
+    match env.storage().persistent().get::<_, i128>(&key) {
+        Some(balance) => {
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, BALANCE_THRESHOLD, BALANCE_EXTEND_TO);
+            balance
+        }
+        None => 0,
+    }
+}
+
+fn write_balance(env: &Env, id: &Address, amount: i128) {
+    let key = (BALANCE, id.clone());
+    env.storage().persistent().set(&key, &amount);
+    env.storage()
+        .persistent()
+        .extend_ttl(&key, BALANCE_THRESHOLD, BALANCE_EXTEND_TO);
+}
+
+pub fn receive_balance(env: &Env, id: &Address, amount: i128) {
+    let balance = read_balance(env, id)
+        .checked_add(amount)
+        .unwrap_or_else(|| panic_with_error!(env, PointsError::ArithmeticOverflow));
+    write_balance(env, id, balance);
+}
+
+pub fn spend_balance(env: &Env, id: &Address, amount: i128) {
+    let balance = read_balance(env, id);
+    if balance < amount {
+        panic_with_error!(env, PointsError::InsufficientBalance)
+    }
+    write_balance(env, id, balance - amount);
+}
+
+/// The allowance of `spender` over `from`, treating an expired approval as
+/// zero.
+pub fn read_allowance(env: &Env, from: &Address, spender: &Address) -> Allowance {
+    let key = (ALLOWANCE, from.clone(), spender.clone());
+    match env.storage().temporary().get::<_, Allowance>(&key) {
+        Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+        Some(allowance) => Allowance {
+            amount: 0,
+            expiration_ledger: allowance.expiration_ledger,
+        },
+        None => Allowance {
+            amount: 0,
+            expiration_ledger: 0,
+        },
+    }
+}
+
+pub fn write_allowance(
+    env: &Env,
+    from: &Address,
+    spender: &Address,
+    amount: i128,
+    expiration_ledger: u32,
+) {
+    if amount > 0 && expiration_ledger < env.ledger().sequence() {
+        panic_with_error!(env, PointsError::InvalidExpiration)
+    }
+
+    let key = (ALLOWANCE, from.clone(), spender.clone());
+    env.storage().temporary().set(
+        &key,
+        &Allowance {
+            amount,
+            expiration_ledger,
+        },
+    );
+    if amount > 0 {
+        let live_for = expiration_ledger - env.ledger().sequence();
+        env.storage()
+            .temporary()
+            .extend_ttl(&key, live_for, live_for);
+    }
+}
+
+pub fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+    let allowance = read_allowance(env, from, spender);
+    if allowance.amount < amount {
+        panic_with_error!(env, PointsError::InsufficientAllowance)
+    }
+    if amount > 0 {
+        write_allowance(
+            env,
+            from,
+            spender,
+            allowance.amount - amount,
+            allowance.expiration_ledger,
+        );
+    }
+}
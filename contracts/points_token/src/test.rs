@@ -0,0 +1,125 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, Events},
+    token::TokenClient,
+    vec, IntoVal,
+};
+
+fn setup(env: &Env) -> (PointsTokenClient<'_>, Address) {
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, PointsToken);
+    let client = PointsTokenClient::new(env, &contract_id);
+    let admin = Address::generate(env);
+    client.initialize(
+        &admin,
+        &0,
+        &String::from_str(env, "Loyalty Points"),
+        &String::from_str(env, "PTS"),
+    );
+    (client, admin)
+}
+
+fn assert_error<T: core::fmt::Debug, E: core::fmt::Debug>(
+    result: Result<Result<T, E>, Result<soroban_sdk::Error, soroban_sdk::InvokeError>>,
+    error: PointsError,
+) {
+    assert_eq!(result.unwrap_err(), Ok(error.into()));
+}
+
+#[test]
+fn test_initialize() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let token = TokenClient::new(&env, &client.address);
+
+    assert_eq!(client.admin(), admin);
+    assert_eq!(token.decimals(), 0);
+    assert_eq!(token.name(), String::from_str(&env, "Loyalty Points"));
+    assert_eq!(token.symbol(), String::from_str(&env, "PTS"));
+
+    let result = client.try_initialize(
+        &admin,
+        &0,
+        &String::from_str(&env, "Again"),
+        &String::from_str(&env, "AGN"),
+    );
+    assert_error(result, PointsError::AlreadyInitialized);
+}
+
+#[test]
+fn test_mint_requires_minter() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let minter = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    assert_error(
+        client.try_mint(&minter, &user, &10),
+        PointsError::NotAuthorized,
+    );
+
+    client.set_minter(&minter, &true);
+    assert!(client.is_minter(&minter));
+    client.mint(&minter, &user, &10);
+    assert_eq!(
+        env.events().all().slice(env.events().all().len() - 1..),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (symbol_short!("mint"), minter.clone(), user.clone()).into_val(&env),
+                10_i128.into_val(&env),
+            )
+        ]
+    );
+
+    client.mint(&admin, &user, &5);
+    assert_eq!(client.balance(&user), 15);
+
+    client.set_minter(&minter, &false);
+    assert_error(
+        client.try_mint(&minter, &user, &10),
+        PointsError::NotAuthorized,
+    );
+    assert_error(
+        client.try_mint(&admin, &user, &-1),
+        PointsError::NegativeAmount,
+    );
+}
+
+#[test]
+fn test_transfer_approve_and_burn() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let spender = Address::generate(&env);
+
+    client.mint(&admin, &alice, &100);
+    client.transfer(&alice, &bob, &30);
+    assert_eq!(client.balance(&alice), 70);
+    assert_eq!(client.balance(&bob), 30);
+    assert_error(
+        client.try_transfer(&bob, &alice, &31),
+        PointsError::InsufficientBalance,
+    );
+
+    client.approve(&alice, &spender, &20, &1000);
+    assert_eq!(client.allowance(&alice, &spender), 20);
+    client.transfer_from(&spender, &alice, &bob, &15);
+    assert_eq!(client.allowance(&alice, &spender), 5);
+    assert_eq!(client.balance(&bob), 45);
+    assert_error(
+        client.try_transfer_from(&spender, &alice, &bob, &6),
+        PointsError::InsufficientAllowance,
+    );
+
+    client.burn_from(&spender, &alice, &5);
+    client.burn(&bob, &45);
+    assert_eq!(client.balance(&alice), 50);
+    assert_eq!(client.balance(&bob), 0);
+    assert_eq!(client.allowance(&alice, &spender), 0);
+}
@@ -0,0 +1,221 @@
+#![no_std]
+
+//! Non-transferable proof-of-redemption receipts.
+//!
+//! Utility contracts appointed as minters issue one receipt per redemption.
+//! Each receipt records the issuing contract, the `utility_id` redeemed and
+//! the redemption timestamp, stays with the address it was minted to, and
+//! is indexed per owner so wallets and other utilities can look it up.
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, Address,
+    Env, IntoVal, String, Symbol, Val, Vec,
+};
+
+mod test;
+
+pub const ADMIN: Symbol = symbol_short!("ADMIN");
+pub const MINTER: Symbol = symbol_short!("MINTER");
+pub const NAME: Symbol = symbol_short!("NAME");
+pub const SYMBOL: Symbol = symbol_short!("SYMBOL");
+pub const TOKEN_COUNT: Symbol = symbol_short!("TOKEN_CNT");
+pub const RECEIPT: Symbol = symbol_short!("RECEIPT");
+pub const OWNED: Symbol = symbol_short!("OWNED");
+pub const OWNED_COUNT: Symbol = symbol_short!("OWNED_CNT");
+pub const HELD: Symbol = symbol_short!("HELD");
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+const TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+const TTL_THRESHOLD: u32 = TTL_EXTEND_TO - DAY_IN_LEDGERS;
+
+/// Maximum number of token ids returned by `tokens_of`.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ReceiptError {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    NotAuthorized = 3,
+    TokenNotFound = 4,
+    NonTransferable = 5,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptMetadata {
+    pub owner: Address,
+    pub issuer: Address,
+    pub utility_id: u64,
+    pub redeemed_at: u64,
+}
+
+fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+/// Keeps the contract instance, and with it the admin, minters and
+/// counters, alive while the contract is in use.
+fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+fn read_admin(env: &Env) -> Address {
+    extend_instance(env);
+    env.storage()
+        .instance()
+        .get(&ADMIN)
+        .unwrap_or_else(|| panic_with_error!(env, ReceiptError::NotInitialized))
+}
+
+fn set_persistent<K, V>(env: &Env, key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    env.storage().persistent().set(key, value);
+    extend_persistent(env, key);
+}
+
+#[contract]
+pub struct ReceiptNft;
+
+#[contractimpl]
+impl ReceiptNft {
+    pub fn initialize(env: Env, admin: Address, name: String, symbol: String) {
+        if env.storage().instance().has(&ADMIN) {
+            panic_with_error!(&env, ReceiptError::AlreadyInitialized)
+        }
+        env.storage().instance().set(&ADMIN, &admin);
+        env.storage().instance().set(&NAME, &name);
+        env.storage().instance().set(&SYMBOL, &symbol);
+        extend_instance(&env);
+    }
+
+    /// Allows or stops `minter`, typically a utility contract, issuing
+    /// receipts.
+    pub fn set_minter(env: Env, minter: Address, allowed: bool) {
+        read_admin(&env).require_auth();
+        let key = (MINTER, minter);
+        if allowed {
+            env.storage().instance().set(&key, &true);
+        } else {
+            env.storage().instance().remove(&key);
+        }
+    }
+
+    pub fn is_minter(env: Env, minter: Address) -> bool {
+        extend_instance(&env);
+        env.storage()
+            .instance()
+            .get::<(Symbol, Address), bool>(&(MINTER, minter))
+            .unwrap_or(false)
+    }
+
+    /// Mints a receipt for `to` redeeming `utility_id` on `minter` and
+    /// returns its token id.
+    pub fn mint(env: Env, minter: Address, to: Address, utility_id: u64) -> u64 {
+        minter.require_auth();
+        if !Self::is_minter(env.clone(), minter.clone()) {
+            panic_with_error!(&env, ReceiptError::NotAuthorized)
+        }
+
+        let token_id = Self::total_supply(env.clone());
+        env.storage().instance().set(&TOKEN_COUNT, &(token_id + 1));
+
+        let receipt = ReceiptMetadata {
+            owner: to.clone(),
+            issuer: minter.clone(),
+            utility_id,
+            redeemed_at: env.ledger().timestamp(),
+        };
+        set_persistent(&env, &(RECEIPT, token_id), &receipt);
+
+        let owned = Self::balance(env.clone(), to.clone());
+        set_persistent(&env, &(OWNED, to.clone(), owned), &token_id);
+        set_persistent(&env, &(OWNED_COUNT, to.clone()), &(owned + 1));
+        set_persistent(&env, &(HELD, to.clone(), minter.clone(), utility_id), &true);
+
+        env.events()
+            .publish((symbol_short!("mint"), minter, to), (token_id, utility_id));
+        token_id
+    }
+
+    /// Receipts cannot change hands.
+    pub fn transfer(env: Env, _from: Address, _to: Address, _token_id: u64) {
+        panic_with_error!(&env, ReceiptError::NonTransferable)
+    }
+
+    pub fn name(env: Env) -> String {
+        extend_instance(&env);
+        env.storage()
+            .instance()
+            .get(&NAME)
+            .unwrap_or_else(|| panic_with_error!(&env, ReceiptError::NotInitialized))
+    }
+
+    pub fn symbol(env: Env) -> String {
+        extend_instance(&env);
+        env.storage()
+            .instance()
+            .get(&SYMBOL)
+            .unwrap_or_else(|| panic_with_error!(&env, ReceiptError::NotInitialized))
+    }
+
+    pub fn total_supply(env: Env) -> u64 {
+        extend_instance(&env);
+        env.storage().instance().get(&TOKEN_COUNT).unwrap_or(0)
+    }
+
+    pub fn metadata(env: Env, token_id: u64) -> ReceiptMetadata {
+        extend_instance(&env);
+        env.storage()
+            .persistent()
+            .get(&(RECEIPT, token_id))
+            .unwrap_or_else(|| panic_with_error!(&env, ReceiptError::TokenNotFound))
+    }
+
+    pub fn owner_of(env: Env, token_id: u64) -> Address {
+        Self::metadata(env, token_id).owner
+    }
+
+    pub fn balance(env: Env, owner: Address) -> u32 {
+        extend_instance(&env);
+        env.storage()
+            .persistent()
+            .get(&(OWNED_COUNT, owner))
+            .unwrap_or(0)
+    }
+
+    /// Token ids owned by `owner` in mint order starting at `cursor`.
+    pub fn tokens_of(env: Env, owner: Address, cursor: u32, limit: u32) -> Vec<u64> {
+        let end = Self::balance(env.clone(), owner.clone())
+            .min(cursor.saturating_add(limit.min(MAX_PAGE_SIZE)));
+
+        let mut tokens = Vec::new(&env);
+        for position in cursor..end {
+            if let Some(token_id) =
+                env.storage()
+                    .persistent()
+                    .get::<_, u64>(&(OWNED, owner.clone(), position))
+            {
+                tokens.push_back(token_id);
+            }
+        }
+        tokens
+    }
+
+    /// Whether `owner` holds a receipt from `issuer` for `utility_id`.
+    pub fn has_receipt(env: Env, owner: Address, issuer: Address, utility_id: u64) -> bool {
+        extend_instance(&env);
+        env.storage()
+            .persistent()
+            .get::<_, bool>(&(HELD, owner, issuer, utility_id))
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,76 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::{
+    testutils::{Address as _, Ledger},
+    vec,
+};
+
+fn setup(env: &Env) -> (ReceiptNftClient<'_>, Address) {
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, ReceiptNft);
+    let client = ReceiptNftClient::new(env, &contract_id);
+    let admin = Address::generate(env);
+    client.initialize(
+        &admin,
+        &String::from_str(env, "Redemption Receipt"),
+        &String::from_str(env, "RCPT"),
+    );
+    (client, admin)
+}
+
+fn assert_error<T: core::fmt::Debug, E: core::fmt::Debug>(
+    result: Result<Result<T, E>, Result<soroban_sdk::Error, soroban_sdk::InvokeError>>,
+    error: ReceiptError,
+) {
+    assert_eq!(result.unwrap_err(), Ok(error.into()));
+}
+
+#[test]
+fn test_mint_and_query() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let issuer = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    assert_eq!(client.symbol(), String::from_str(&env, "RCPT"));
+    assert_error(
+        client.try_initialize(
+            &admin,
+            &String::from_str(&env, "Again"),
+            &String::from_str(&env, "AGN"),
+        ),
+        ReceiptError::AlreadyInitialized,
+    );
+    assert_error(
+        client.try_mint(&issuer, &user, &7),
+        ReceiptError::NotAuthorized,
+    );
+
+    client.set_minter(&issuer, &true);
+    env.ledger().with_mut(|l| l.timestamp = 1234);
+    assert_eq!(client.mint(&issuer, &user, &7), 0);
+    assert_eq!(client.mint(&issuer, &user, &8), 1);
+
+    assert_eq!(
+        client.metadata(&0),
+        ReceiptMetadata {
+            owner: user.clone(),
+            issuer: issuer.clone(),
+            utility_id: 7,
+            redeemed_at: 1234,
+        }
+    );
+    assert_eq!(client.owner_of(&1), user);
+    assert_eq!(client.balance(&user), 2);
+    assert_eq!(client.tokens_of(&user, &1, &10), vec![&env, 1]);
+    assert!(client.has_receipt(&user, &issuer, &7));
+    assert!(!client.has_receipt(&user, &issuer, &9));
+    assert!(!client.has_receipt(&user, &admin, &7));
+
+    assert_error(
+        client.try_transfer(&user, &admin, &0),
+        ReceiptError::NonTransferable,
+    );
+    assert_error(client.try_metadata(&2), ReceiptError::TokenNotFound);
+}
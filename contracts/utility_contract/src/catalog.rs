@@ -0,0 +1,292 @@
+//! Points redemption catalog.
+//!
+//! Providers list items at a price in a points token with a limited stock.
+//! Redeeming an item burns the price from the user's points and hands out
+//! the item: a token payout, extra usages on a claimed utility, or tickets
+//! in a ticket-weighted raffle. Token payouts are escrowed from the
+//! provider for the whole stock, and topped up or returned as the stock
+//! changes. Every redemption is recorded under `(PURCHASE, item_id, n)`.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol, Vec};
+
+use crate::structs::{
+    CatalogItem, CatalogPage, CatalogPurchase, CatalogReward, EntryWeight, RewardError, Selection,
+    UsageType, UtilityError,
+};
+use crate::{config, events, raffle, storage, UtilityContract, UtilityContractClient};
+
+pub const CATALOG: Symbol = symbol_short!("CATALOG");
+pub const CATALOG_COUNT: Symbol = symbol_short!("CAT_CNT");
+pub const PURCHASE: Symbol = symbol_short!("PURCHASE");
+
+pub fn item_count(env: &Env) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<Symbol, u64>(&CATALOG_COUNT)
+        .unwrap_or(0)
+}
+
+pub fn load_item(env: &Env, item_id: u64) -> Option<CatalogItem> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), CatalogItem>(&(CATALOG, item_id))
+}
+
+fn save_item(env: &Env, item: &CatalogItem) {
+    let key = (CATALOG, item.id);
+    env.storage().persistent().set(&key, item);
+    config::extend_persistent(env, &key);
+}
+
+fn read_item(env: &Env, item_id: u64) -> CatalogItem {
+    load_item(env, item_id).unwrap_or_else(|| panic_with_error!(env, RewardError::ItemNotFound))
+}
+
+/// Checks that `provider` may list `reward`.
+fn validate(env: &Env, provider: &Address, reward: &CatalogReward) {
+    let (utility_id, count) = match reward {
+        CatalogReward::Token(_, amount) => {
+            if *amount <= 0 {
+                panic_with_error!(env, RewardError::InvalidCatalogItem)
+            }
+            return;
+        }
+        CatalogReward::Usages(utility_id, count)
+        | CatalogReward::RaffleTickets(utility_id, count) => (*utility_id, *count),
+    };
+    if count == 0 {
+        panic_with_error!(env, RewardError::InvalidCatalogItem)
+    }
+
+    let u = UtilityContract::get_utility(env.clone(), utility_id);
+    if u.provider != *provider {
+        panic_with_error!(env, UtilityError::NotAuthorized)
+    }
+    let fits = match reward {
+        CatalogReward::Usages(_, _) => u.usage_type == UsageType::Limited,
+        _ => u.selection == Selection::Raffle && u.raffle.weight == EntryWeight::Tickets,
+    };
+    if !fits {
+        panic_with_error!(env, RewardError::InvalidCatalogItem)
+    }
+}
+
+/// Amount of a token payout needed to back `stock` redemptions.
+fn escrow_for(env: &Env, amount: i128, stock: u32) -> i128 {
+    amount
+        .checked_mul(stock as i128)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow))
+}
+
+fn deliver(env: &Env, item: &CatalogItem, user: &Address, nft: Option<Address>) {
+    match &item.reward {
+        CatalogReward::Token(token_address, amount) => {
+            token::TokenClient::new(env, token_address).transfer(
+                &env.current_contract_address(),
+                user,
+                amount,
+            );
+        }
+        CatalogReward::Usages(utility_id, count) => {
+            let nft = nft.unwrap_or_else(|| panic_with_error!(env, UtilityError::MissingParameter));
+            let u = UtilityContract::get_utility(env.clone(), *utility_id);
+            if u.paused {
+                panic_with_error!(env, UtilityError::UtilityPaused)
+            }
+            if !UtilityContract::check_nft_ownership(env.clone(), nft.clone(), user.clone()) {
+                panic_with_error!(env, UtilityError::NotAuthorized)
+            }
+            let mut token_utility = storage::load_token_utility(env, &nft, *utility_id)
+                .unwrap_or_else(|| panic_with_error!(env, UtilityError::UtilityNotClaimed));
+            token_utility.usage = token_utility.usage.saturating_add(*count);
+            storage::save_token_utility(env, &token_utility);
+        }
+        CatalogReward::RaffleTickets(utility_id, count) => {
+            let u = UtilityContract::get_utility(env.clone(), *utility_id);
+            raffle::require_open(env, &u);
+            raffle::add_tickets(env, *utility_id, user, *count);
+            raffle::add_weight(env, *utility_id, user, *count);
+        }
+    }
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Lists `reward` at `price` points of `points_token`, with `stock`
+    /// redemptions available. Token payouts are escrowed from `provider`.
+    pub fn add_catalog_item(
+        env: Env,
+        provider: Address,
+        points_token: Address,
+        price: i128,
+        stock: u32,
+        reward: CatalogReward,
+    ) -> CatalogItem {
+        provider.require_auth();
+        if price <= 0 {
+            panic_with_error!(&env, RewardError::InvalidCatalogItem)
+        }
+        validate(&env, &provider, &reward);
+
+        if let CatalogReward::Token(token_address, amount) = &reward {
+            let escrow = escrow_for(&env, *amount, stock);
+            if escrow > 0 {
+                token::TokenClient::new(&env, token_address).transfer(
+                    &provider,
+                    &env.current_contract_address(),
+                    &escrow,
+                );
+            }
+        }
+
+        let item = CatalogItem {
+            id: item_count(&env),
+            provider: provider.clone(),
+            points_token,
+            price,
+            stock,
+            redeemed: 0,
+            reward,
+        };
+        save_item(&env, &item);
+        env.storage()
+            .persistent()
+            .set(&CATALOG_COUNT, &(item.id + 1));
+
+        events::catalog_item_listed(&env, item.id, provider, price, stock);
+        item
+    }
+
+    pub fn set_catalog_price(env: Env, item_id: u64, sender: Address, price: i128) {
+        sender.require_auth();
+        let mut item = read_item(&env, item_id);
+
+        if item.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if price <= 0 {
+            panic_with_error!(&env, RewardError::InvalidCatalogItem)
+        }
+
+        let old_price = item.price;
+        item.price = price;
+        save_item(&env, &item);
+
+        events::catalog_price_changed(&env, item_id, old_price, price);
+    }
+
+    /// Sets the remaining stock. Token payouts are topped up from or
+    /// returned to the provider to match; a stock of 0 delists the item.
+    pub fn set_catalog_stock(env: Env, item_id: u64, sender: Address, stock: u32) {
+        sender.require_auth();
+        let mut item = read_item(&env, item_id);
+
+        if item.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        if let CatalogReward::Token(token_address, amount) = &item.reward {
+            let client = token::TokenClient::new(&env, token_address);
+            let contract = env.current_contract_address();
+            if stock > item.stock {
+                client.transfer(
+                    &sender,
+                    &contract,
+                    &escrow_for(&env, *amount, stock - item.stock),
+                );
+            } else if stock < item.stock {
+                client.transfer(
+                    &contract,
+                    &sender,
+                    &escrow_for(&env, *amount, item.stock - stock),
+                );
+            }
+        }
+
+        let old_stock = item.stock;
+        item.stock = stock;
+        save_item(&env, &item);
+
+        events::catalog_stock_set(&env, item_id, old_stock, stock);
+    }
+
+    /// Burns the item's price from `user`'s points and hands out the item.
+    /// Usage items are credited to `nft`, which `user` must hold.
+    pub fn redeem_catalog_item(env: Env, item_id: u64, user: Address, nft: Option<Address>) {
+        user.require_auth();
+        let mut item = read_item(&env, item_id);
+
+        if item.stock == 0 {
+            panic_with_error!(&env, RewardError::OutOfStock)
+        }
+
+        token::TokenClient::new(&env, &item.points_token).burn(&user, &item.price);
+        deliver(&env, &item, &user, nft);
+
+        let purchase_key = (PURCHASE, item_id, item.redeemed);
+        env.storage().persistent().set(
+            &purchase_key,
+            &CatalogPurchase {
+                user: user.clone(),
+                price: item.price,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        config::extend_persistent(&env, &purchase_key);
+
+        item.stock -= 1;
+        item.redeemed += 1;
+        save_item(&env, &item);
+
+        events::catalog_item_redeemed(&env, item_id, user, item.price, item.stock);
+    }
+
+    pub fn get_catalog_item(env: Env, item_id: u64) -> CatalogItem {
+        read_item(&env, item_id)
+    }
+
+    /// Catalog items in listing order starting at id `cursor`.
+    pub fn list_catalog_items(env: Env, cursor: u64, limit: u32) -> CatalogPage {
+        let limit = limit.min(config::max_page_size(&env));
+        let count = item_count(&env);
+        let mut items = Vec::new(&env);
+
+        let mut id = cursor;
+        while id < count && items.len() < limit {
+            if let Some(item) = load_item(&env, id) {
+                items.push_back(item);
+            }
+            id += 1;
+        }
+
+        CatalogPage {
+            items,
+            next_cursor: if id < count { Some(id) } else { None },
+        }
+    }
+
+    /// Redemptions of `item_id` in order starting at `cursor`.
+    pub fn list_catalog_purchases(
+        env: Env,
+        item_id: u64,
+        cursor: u32,
+        limit: u32,
+    ) -> Vec<CatalogPurchase> {
+        let limit = limit.min(config::max_page_size(&env));
+        let end = read_item(&env, item_id)
+            .redeemed
+            .min(cursor.saturating_add(limit));
+
+        let mut purchases = Vec::new(&env);
+        for n in cursor..end {
+            if let Some(p) = env
+                .storage()
+                .persistent()
+                .get::<(Symbol, u64, u32), CatalogPurchase>(&(PURCHASE, item_id, n))
+            {
+                purchases.push_back(p);
+            }
+        }
+        purchases
+    }
+}
@@ -0,0 +1,152 @@
+//! Hash-preimage claim codes.
+//!
+//! For giveaways at physical events a provider registers `sha256(code)`
+//! for each printed code. Claiming is two-step so a code seen in a pending
+//! transaction cannot be front-run: the user first commits to
+//! `sha256(code || user.to_xdr())` with `commit_claim_code`, then in a
+//! later ledger reveals the code with `claim_with_code`. Each code pays out
+//! once, and sets the same claimed flag as `claim_reward`. Claims on a
+//! giveaway count against its winner cap and rate limit.
+
+use soroban_sdk::{
+    contractimpl, panic_with_error, symbol_short, xdr::ToXdr, Address, Bytes, BytesN, Env, Symbol,
+    Vec,
+};
+
+use crate::structs::{RewardError, Selection, UtilityError, UtilityType};
+use crate::{
+    config, eligibility, events, giveaway, holding, require_unclaimed, settle_claim, take_reward,
+    UtilityContract, UtilityContractClient,
+};
+
+pub const CODE: Symbol = symbol_short!("CODE");
+pub const CODE_COUNT: Symbol = symbol_short!("CODE_CNT");
+pub const CODE_COMMIT: Symbol = symbol_short!("CODE_CMT");
+
+/// Whether the code hashing to `code_hash` is registered and unused.
+pub fn is_unused(env: &Env, utility_id: u64, code_hash: &BytesN<32>) -> Option<bool> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, BytesN<32>), bool>(&(CODE, utility_id, code_hash.clone()))
+}
+
+/// The commitment `user` must make before claiming with `code`.
+pub fn commitment(env: &Env, code: &Bytes, user: &Address) -> BytesN<32> {
+    let mut input = code.clone();
+    input.append(&user.clone().to_xdr(env));
+    env.crypto().sha256(&input).into()
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Registers the sha256 hashes of claim codes. Hashes already
+    /// registered are skipped. Returns the number of codes on the utility.
+    pub fn add_claim_codes(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        code_hashes: Vec<BytesN<32>>,
+    ) -> u32 {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if code_hashes.len() > config::max_batch_size(&env) {
+            panic_with_error!(&env, UtilityError::BatchTooLarge)
+        }
+
+        let mut added = 0;
+        for hash in code_hashes.iter() {
+            let key = (CODE, utility_id, hash);
+            if env.storage().persistent().has(&key) {
+                continue;
+            }
+            env.storage().persistent().set(&key, &true);
+            config::extend_persistent(&env, &key);
+            added += 1;
+        }
+
+        let count_key = (CODE_COUNT, utility_id);
+        let count = Self::get_claim_code_count(env.clone(), utility_id) + added;
+        env.storage().persistent().set(&count_key, &count);
+        config::extend_persistent(&env, &count_key);
+
+        events::claim_codes_added(&env, utility_id, added);
+        count
+    }
+
+    pub fn get_claim_code_count(env: Env, utility_id: u64) -> u32 {
+        env.storage()
+            .persistent()
+            .get::<(Symbol, u64), u32>(&(CODE_COUNT, utility_id))
+            .unwrap_or(0)
+    }
+
+    pub fn is_claim_code_valid(env: Env, utility_id: u64, code_hash: BytesN<32>) -> bool {
+        is_unused(&env, utility_id, &code_hash).unwrap_or(false)
+    }
+
+    /// Records `user`'s commitment to a code, replacing any earlier one.
+    pub fn commit_claim_code(env: Env, utility_id: u64, user: Address, commitment: BytesN<32>) {
+        user.require_auth();
+        Self::get_utility(env.clone(), utility_id);
+
+        let key = (CODE_COMMIT, utility_id, user);
+        env.storage()
+            .persistent()
+            .set(&key, &(commitment, env.ledger().sequence()));
+        config::extend_persistent(&env, &key);
+    }
+
+    /// Claims `user`'s reward with a registered code committed to in an
+    /// earlier ledger. Only non-raffle utilities accept codes.
+    pub fn claim_with_code(env: Env, utility_id: u64, code: Bytes, user: Address) {
+        user.require_auth();
+        let mut utility = Self::get_utility(env.clone(), utility_id);
+
+        if utility.selection != Selection::All {
+            panic_with_error!(&env, UtilityError::InvalidSelection)
+        }
+        if utility.paused {
+            panic_with_error!(&env, UtilityError::UtilityPaused)
+        }
+        if utility.offer_expiry < env.ledger().timestamp() {
+            panic_with_error!(&env, UtilityError::UtilityExpired)
+        }
+
+        let code_hash: BytesN<32> = env.crypto().sha256(&code).into();
+        match is_unused(&env, utility_id, &code_hash) {
+            Some(true) => {}
+            Some(false) => panic_with_error!(&env, RewardError::ClaimCodeUsed),
+            None => panic_with_error!(&env, RewardError::InvalidClaimCode),
+        }
+
+        let commit_key = (CODE_COMMIT, utility_id, user.clone());
+        let committed = env
+            .storage()
+            .persistent()
+            .get::<_, (BytesN<32>, u32)>(&commit_key);
+        match committed {
+            Some((hash, ledger))
+                if hash == commitment(&env, &code, &user) && ledger < env.ledger().sequence() => {}
+            _ => panic_with_error!(&env, RewardError::ClaimCodeNotCommitted),
+        }
+
+        let amount = take_reward(&env, &mut utility);
+
+        holding::require_held(&env, &utility, None, &user);
+        eligibility::require_eligible(&env, utility_id, &user);
+        require_unclaimed(&env, utility_id, &user);
+        if utility.utility_type == UtilityType::Giveaway {
+            giveaway::record_claim(&env, &utility);
+        }
+
+        env.storage()
+            .persistent()
+            .set(&(CODE, utility_id, code_hash.clone()), &false);
+        env.storage().persistent().remove(&commit_key);
+
+        settle_claim(&env, &utility, &user, amount);
+        events::claim_code_used(&env, utility_id, user, code_hash);
+    }
+}
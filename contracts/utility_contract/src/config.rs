@@ -0,0 +1,87 @@
+//! Contract-wide configuration.
+//!
+//! The [`Config`] lives in instance storage so it shares the contract's TTL.
+//! Paths that do not need the admin fall back to the defaults below when the
+//! contract has not been set up yet.
+
+use soroban_sdk::{panic_with_error, symbol_short, Address, Env, IntoVal, Symbol, Val};
+
+use crate::structs::{Config, ProviderMode, UtilityError};
+
+pub const CONFIG: Symbol = symbol_short!("CONFIG");
+pub const ALLOWED_PROVIDER: Symbol = symbol_short!("ALLOWED");
+
+/// Version of the storage layout written by this build of the contract.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+pub const DEFAULT_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+pub const DEFAULT_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+pub const DEFAULT_MAX_PAGE_SIZE: u32 = 50;
+pub const DEFAULT_MAX_BATCH_SIZE: u32 = 50;
+
+pub fn defaults(admin: Address) -> Config {
+    Config {
+        admin,
+        fee_token: None,
+        creation_fee: 0,
+        platform_fee_bps: 0,
+        ttl_threshold: DEFAULT_TTL_THRESHOLD,
+        ttl_extend_to: DEFAULT_TTL_EXTEND_TO,
+        max_page_size: DEFAULT_MAX_PAGE_SIZE,
+        max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        provider_mode: ProviderMode::Open,
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+pub fn load(env: &Env) -> Option<Config> {
+    let config = env.storage().instance().get::<Symbol, Config>(&CONFIG);
+    if let Some(c) = &config {
+        env.storage()
+            .instance()
+            .extend_ttl(c.ttl_threshold, c.ttl_extend_to);
+    }
+    config
+}
+
+pub fn read(env: &Env) -> Config {
+    load(env).unwrap_or_else(|| panic_with_error!(env, UtilityError::NotInitialized))
+}
+
+pub fn save(env: &Env, config: &Config) {
+    env.storage().instance().set(&CONFIG, config);
+    env.storage()
+        .instance()
+        .extend_ttl(config.ttl_threshold, config.ttl_extend_to);
+}
+
+pub fn max_page_size(env: &Env) -> u32 {
+    load(env).map_or(DEFAULT_MAX_PAGE_SIZE, |c| c.max_page_size)
+}
+
+pub fn max_batch_size(env: &Env) -> u32 {
+    load(env).map_or(DEFAULT_MAX_BATCH_SIZE, |c| c.max_batch_size)
+}
+
+/// Extends a persistent entry using the configured TTLs.
+pub fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    let (threshold, extend_to) = load(env)
+        .map_or((DEFAULT_TTL_THRESHOLD, DEFAULT_TTL_EXTEND_TO), |c| {
+            (c.ttl_threshold, c.ttl_extend_to)
+        });
+    env.storage()
+        .persistent()
+        .extend_ttl(key, threshold, extend_to);
+}
+
+pub fn is_provider_allowed(env: &Env, provider: &Address) -> bool {
+    match load(env).map_or(ProviderMode::Open, |c| c.provider_mode) {
+        ProviderMode::Open => true,
+        ProviderMode::Allowlist => env
+            .storage()
+            .persistent()
+            .get::<(Symbol, Address), bool>(&(ALLOWED_PROVIDER, provider.clone()))
+            .unwrap_or(false),
+    }
+}
@@ -0,0 +1,126 @@
+//! Coupon code pools.
+//!
+//! A provider commits to a utility's discount codes by uploading
+//! `sha256(code)` for each, so the codes themselves never reach the
+//! ledger. Once a utility has a pool, every redemption assigns the next
+//! unused commitment to the redeemer and fails with `OutOfStock` when none
+//! are left. The provider reveals the matching code to the redeemer
+//! off-chain, and either side can check it against the assignment with
+//! `verify_coupon_code`.
+
+use soroban_sdk::{
+    contractimpl, panic_with_error, symbol_short, Address, Bytes, BytesN, Env, Symbol, Vec,
+};
+
+use crate::structs::{CouponInventory, RewardError, Utility, UtilityError};
+use crate::{config, events, UtilityContract, UtilityContractClient};
+
+pub const COUPON: Symbol = symbol_short!("COUPON");
+pub const COUPON_COUNT: Symbol = symbol_short!("CPN_CNT");
+pub const COUPON_NEXT: Symbol = symbol_short!("CPN_NEXT");
+pub const COUPON_USER: Symbol = symbol_short!("CPN_USER");
+
+pub fn inventory(env: &Env, utility_id: u64) -> CouponInventory {
+    let read = |kind: Symbol| {
+        env.storage()
+            .persistent()
+            .get::<(Symbol, u64), u32>(&(kind, utility_id))
+            .unwrap_or(0)
+    };
+    CouponInventory {
+        total: read(COUPON_COUNT),
+        assigned: read(COUPON_NEXT),
+    }
+}
+
+fn assigned_to(env: &Env, utility_id: u64, user: &Address) -> Vec<u32> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, Address), Vec<u32>>(&(COUPON_USER, utility_id, user.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Assigns the next unused code of `u`'s pool to `user`. Utilities
+/// without a pool are left alone.
+pub fn assign(env: &Env, u: &Utility, user: &Address) {
+    let stock = inventory(env, u.id);
+    if stock.total == 0 {
+        return;
+    }
+    if stock.assigned >= stock.total {
+        panic_with_error!(env, RewardError::OutOfStock)
+    }
+
+    let index = stock.assigned;
+    let next_key = (COUPON_NEXT, u.id);
+    env.storage().persistent().set(&next_key, &(index + 1));
+    config::extend_persistent(env, &next_key);
+
+    let mut codes = assigned_to(env, u.id, user);
+    codes.push_back(index);
+    let user_key = (COUPON_USER, u.id, user.clone());
+    env.storage().persistent().set(&user_key, &codes);
+    config::extend_persistent(env, &user_key);
+
+    events::coupon_assigned(env, u.id, user.clone(), index, stock.total - index - 1);
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Appends the `sha256` commitments `codes` to the utility's coupon
+    /// pool. From then on every redemption needs a code.
+    pub fn add_coupon_codes(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        codes: Vec<BytesN<32>>,
+    ) -> CouponInventory {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if codes.len() > config::max_batch_size(&env) {
+            panic_with_error!(&env, UtilityError::BatchTooLarge)
+        }
+
+        let mut stock = inventory(&env, utility_id);
+        for code in codes.iter() {
+            let key = (COUPON, utility_id, stock.total);
+            env.storage().persistent().set(&key, &code);
+            config::extend_persistent(&env, &key);
+            stock.total += 1;
+        }
+        let count_key = (COUPON_COUNT, utility_id);
+        env.storage().persistent().set(&count_key, &stock.total);
+        config::extend_persistent(&env, &count_key);
+
+        events::coupons_added(&env, utility_id, codes.len(), stock.total);
+        stock
+    }
+
+    pub fn get_coupon_inventory(env: Env, utility_id: u64) -> CouponInventory {
+        inventory(&env, utility_id)
+    }
+
+    /// Commitments of the codes assigned to `user` for `utility_id`,
+    /// oldest first.
+    pub fn get_coupon_codes(env: Env, utility_id: u64, user: Address) -> Vec<BytesN<32>> {
+        let mut codes = Vec::new(&env);
+        for index in assigned_to(&env, utility_id, &user).iter() {
+            if let Some(code) = env
+                .storage()
+                .persistent()
+                .get::<(Symbol, u64, u32), BytesN<32>>(&(COUPON, utility_id, index))
+            {
+                codes.push_back(code);
+            }
+        }
+        codes
+    }
+
+    /// Whether `code` is one of the codes assigned to `user`.
+    pub fn verify_coupon_code(env: Env, utility_id: u64, user: Address, code: Bytes) -> bool {
+        let code_hash: BytesN<32> = env.crypto().sha256(&code).into();
+        Self::get_coupon_codes(env, utility_id, user).contains(code_hash)
+    }
+}
@@ -0,0 +1,134 @@
+//! Delegated redemption for rented NFTs.
+//!
+//! The holder of an NFT can let another address redeem one of its utilities
+//! until a deadline and for a capped number of uses, without handing over the
+//! token. Only one delegation exists per `(token, utility_id)` and, while it
+//! is active, the holder's own redemptions are refused.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, Address, Env, Symbol};
+
+use crate::structs::{Delegation, UtilityError};
+use crate::{config, events, UtilityContract, UtilityContractClient};
+
+pub const DELEGATION: Symbol = symbol_short!("DELEGATE");
+
+pub fn load(env: &Env, token: &Address, utility_id: u64) -> Option<Delegation> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address, u64), Delegation>(&(DELEGATION, token.clone(), utility_id))
+}
+
+fn save(env: &Env, d: &Delegation) {
+    let key = (DELEGATION, d.token.clone(), d.utility_id);
+    env.storage().persistent().set(&key, d);
+    config::extend_persistent(env, &key);
+}
+
+pub fn is_active(env: &Env, d: &Delegation) -> bool {
+    env.ledger().timestamp() < d.expires_at && d.uses < d.max_uses
+}
+
+/// Checks that `user` may redeem `utility_id` on `token`, either as the
+/// holder or as the active delegate. Returns the delegation when the
+/// redemption is made through one.
+pub fn authorize_redemption(
+    env: &Env,
+    token: &Address,
+    utility_id: u64,
+    user: &Address,
+) -> Option<Delegation> {
+    let owns = |who: &Address| {
+        UtilityContract::check_nft_ownership(env.clone(), token.clone(), who.clone())
+    };
+
+    match load(env, token, utility_id).filter(|d| is_active(env, d)) {
+        Some(d) if d.delegate == *user => {
+            // A delegation does not survive the NFT leaving its grantor.
+            if !owns(&d.owner) {
+                panic_with_error!(env, UtilityError::NotAuthorized)
+            }
+            Some(d)
+        }
+        Some(_) => {
+            if owns(user) {
+                panic_with_error!(env, UtilityError::DelegationActive)
+            }
+            panic_with_error!(env, UtilityError::NotAuthorized)
+        }
+        None => {
+            if !owns(user) {
+                panic_with_error!(env, UtilityError::NotAuthorized)
+            }
+            None
+        }
+    }
+}
+
+pub fn record_use(env: &Env, mut d: Delegation) {
+    d.uses += 1;
+    save(env, &d);
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Lets `delegate` redeem `utility_id` on the caller's NFT until
+    /// `expires_at`, at most `max_uses` times.
+    pub fn delegate_utility(
+        env: Env,
+        token_address: Address,
+        utility_id: u64,
+        owner: Address,
+        delegate: Address,
+        expires_at: u64,
+        max_uses: u64,
+    ) -> Delegation {
+        owner.require_auth();
+        Self::get_utility(env.clone(), utility_id);
+
+        if !Self::check_nft_ownership(env.clone(), token_address.clone(), owner.clone()) {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if delegate == owner || max_uses == 0 || expires_at <= env.ledger().timestamp() {
+            panic_with_error!(&env, UtilityError::InvalidDelegation)
+        }
+        if load(&env, &token_address, utility_id).is_some_and(|d| is_active(&env, &d)) {
+            panic_with_error!(&env, UtilityError::DelegationActive)
+        }
+
+        let d = Delegation {
+            token: token_address,
+            utility_id,
+            owner,
+            delegate,
+            expires_at,
+            max_uses,
+            uses: 0,
+        };
+        save(&env, &d);
+
+        events::delegation_created(&env, &d);
+        d
+    }
+
+    /// Removes the delegation on `token_address`. Any current holder of the
+    /// NFT may revoke, so a buyer is not bound by the seller's rental.
+    pub fn revoke_delegation(env: Env, token_address: Address, utility_id: u64, owner: Address) {
+        owner.require_auth();
+
+        let d = load(&env, &token_address, utility_id)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::DelegationNotFound));
+        if !Self::check_nft_ownership(env.clone(), token_address.clone(), owner.clone()) {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        env.storage()
+            .persistent()
+            .remove(&(DELEGATION, token_address, utility_id));
+
+        events::delegation_revoked(&env, &d, owner);
+    }
+
+    pub fn get_delegation(env: Env, token_address: Address, utility_id: u64) -> Option<Delegation> {
+        load(&env, &token_address, utility_id)
+    }
+}
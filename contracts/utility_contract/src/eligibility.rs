@@ -0,0 +1,181 @@
+//! Composable eligibility rules.
+//!
+//! A provider may attach one [`EligibilityRule`] tree to a utility. The tree
+//! is evaluated for the user on every join, claim and redemption path; a
+//! utility without a rule admits everyone. Trees are size and depth limited
+//! when they are set so evaluation stays within budget.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol, Vec};
+
+use crate::structs::{EligibilityRule, UtilityError};
+use crate::{config, events, holding, receipts, UtilityContract, UtilityContractClient};
+
+pub const RULES: Symbol = symbol_short!("RULES");
+pub const LIST: Symbol = symbol_short!("LIST");
+
+/// Maximum number of nodes in a rule tree.
+pub const MAX_RULE_NODES: u32 = 32;
+/// Maximum nesting of `AnyOf`, `AllOf` and `Not`.
+pub const MAX_RULE_DEPTH: u32 = 4;
+
+pub fn load(env: &Env, utility_id: u64) -> Option<EligibilityRule> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), EligibilityRule>(&(RULES, utility_id))
+}
+
+pub fn is_listed(env: &Env, utility_id: u64, list: Symbol, user: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, Symbol, Address), bool>(&(LIST, utility_id, list, user.clone()))
+        .unwrap_or(false)
+}
+
+/// Returns the number of nodes in `rule`, failing with `InvalidRule` when
+/// the tree is malformed or exceeds the size or depth limits.
+fn validate(env: &Env, rule: &EligibilityRule, depth: u32) -> u32 {
+    if depth > MAX_RULE_DEPTH {
+        panic_with_error!(env, UtilityError::InvalidRule)
+    }
+
+    let nodes = match rule {
+        EligibilityRule::AnyOf(rules) | EligibilityRule::AllOf(rules) => {
+            if rules.is_empty() {
+                panic_with_error!(env, UtilityError::InvalidRule)
+            }
+            rules
+                .iter()
+                .fold(1, |n, r| n + validate(env, &r, depth + 1))
+        }
+        EligibilityRule::Not(rules) => {
+            if rules.len() != 1 {
+                panic_with_error!(env, UtilityError::InvalidRule)
+            }
+            1 + validate(env, &rules.get_unchecked(0), depth + 1)
+        }
+        EligibilityRule::OwnsCollection(collections) => {
+            if collections.is_empty() {
+                panic_with_error!(env, UtilityError::InvalidRule)
+            }
+            collections.len()
+        }
+        EligibilityRule::MinTokenBalance(_, min) => {
+            if *min <= 0 {
+                panic_with_error!(env, UtilityError::InvalidRule)
+            }
+            1
+        }
+        EligibilityRule::OnWhitelist(_)
+        | EligibilityRule::HeldSince(_, _)
+        | EligibilityRule::HasReceipt(_, _)
+        | EligibilityRule::MarkedEligible => 1,
+    };
+
+    if nodes > MAX_RULE_NODES {
+        panic_with_error!(env, UtilityError::InvalidRule)
+    }
+    nodes
+}
+
+fn evaluate(env: &Env, utility_id: u64, rule: &EligibilityRule, user: &Address) -> bool {
+    match rule {
+        EligibilityRule::AnyOf(rules) => rules.iter().any(|r| evaluate(env, utility_id, &r, user)),
+        EligibilityRule::AllOf(rules) => rules.iter().all(|r| evaluate(env, utility_id, &r, user)),
+        EligibilityRule::Not(rules) => !evaluate(env, utility_id, &rules.get_unchecked(0), user),
+        EligibilityRule::OwnsCollection(collections) => collections
+            .iter()
+            .any(|c| UtilityContract::check_nft_ownership(env.clone(), c, user.clone())),
+        EligibilityRule::MinTokenBalance(token_address, min) => {
+            token::TokenClient::new(env, token_address).balance(user) >= *min
+        }
+        EligibilityRule::OnWhitelist(list) => is_listed(env, utility_id, list.clone(), user),
+        EligibilityRule::HeldSince(token_address, period) => {
+            holding::has_held_for(env, token_address, user, *period)
+        }
+        EligibilityRule::HasReceipt(receipt_nft, id) => {
+            receipts::has_receipt(env, receipt_nft, user, *id)
+        }
+        EligibilityRule::MarkedEligible => {
+            UtilityContract::is_eligible(env.clone(), utility_id, user.clone())
+        }
+    }
+}
+
+pub fn require_eligible(env: &Env, utility_id: u64, user: &Address) {
+    if !UtilityContract::evaluate_eligibility(env.clone(), utility_id, user.clone()) {
+        panic_with_error!(env, UtilityError::NotEligible)
+    }
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Evaluates the utility's rule tree for `user`. Utilities without a
+    /// rule admit everyone.
+    pub fn evaluate_eligibility(env: Env, utility_id: u64, user: Address) -> bool {
+        match load(&env, utility_id) {
+            Some(rule) => evaluate(&env, utility_id, &rule, &user),
+            None => true,
+        }
+    }
+
+    pub fn set_eligibility_rule(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        rule: Option<EligibilityRule>,
+    ) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        let key = (RULES, utility_id);
+        match &rule {
+            Some(r) => {
+                validate(&env, r, 0);
+                env.storage().persistent().set(&key, r);
+                config::extend_persistent(&env, &key);
+            }
+            None => env.storage().persistent().remove(&key),
+        }
+
+        events::eligibility_rule_set(&env, utility_id, rule.is_some());
+    }
+
+    pub fn get_eligibility_rule(env: Env, utility_id: u64) -> Option<EligibilityRule> {
+        load(&env, utility_id)
+    }
+
+    /// Adds `users` to or removes them from the utility's named `list`, as
+    /// referenced by `EligibilityRule::OnWhitelist`.
+    pub fn set_list_members(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        list: Symbol,
+        users: Vec<Address>,
+        member: bool,
+    ) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if users.len() > config::max_batch_size(&env) {
+            panic_with_error!(&env, UtilityError::BatchTooLarge)
+        }
+
+        for user in users.iter() {
+            let key = (LIST, utility_id, list.clone(), user);
+            if member {
+                env.storage().persistent().set(&key, &true);
+                config::extend_persistent(&env, &key);
+            } else {
+                env.storage().persistent().remove(&key);
+            }
+        }
+    }
+
+    pub fn is_list_member(env: Env, utility_id: u64, list: Symbol, user: Address) -> bool {
+        is_listed(&env, utility_id, list, &user)
+    }
+}
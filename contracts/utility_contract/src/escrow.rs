@@ -0,0 +1,105 @@
+//! Per-utility reward escrow.
+//!
+//! The contract holds every `Receipt::External` reward pool in the same
+//! token balances as ticket proceeds, settlement escrows and accrued fees.
+//! Each utility's pool is accounted for under `(REWARD_ESCROW, utility_id)`,
+//! apart from the ticket escrow of the same utility, so payouts, clawbacks
+//! and sweeps only ever spend what that utility's provider deposited.
+//!
+//! Once a utility can no longer pay out, because it was cancelled, its
+//! raffle was called off or its offer expired, the provider takes back
+//! whatever vesting grants do not still owe with `reclaim_reward_escrow`.
+//! Drawn raffles return their unclaimed prizes through `sweep_unclaimed`.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol};
+
+use crate::structs::{RewardError, Selection, UtilityError};
+use crate::{
+    config, events, reward_token, storage, vesting, UtilityContract, UtilityContractClient,
+};
+
+pub const REWARD_ESCROW: Symbol = symbol_short!("RWD_ESC");
+
+pub fn balance(env: &Env, utility_id: u64) -> i128 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), i128>(&(REWARD_ESCROW, utility_id))
+        .unwrap_or(0)
+}
+
+fn set_balance(env: &Env, utility_id: u64, amount: i128) {
+    let key = (REWARD_ESCROW, utility_id);
+    env.storage().persistent().set(&key, &amount);
+    config::extend_persistent(env, &key);
+}
+
+/// Credits `amount`, already received by the contract, to the pool of
+/// `utility_id`.
+pub fn deposit(env: &Env, utility_id: u64, amount: i128) {
+    let total = balance(env, utility_id)
+        .checked_add(amount)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    set_balance(env, utility_id, total);
+}
+
+/// Takes `amount` out of the pool of `utility_id` without moving tokens.
+pub fn debit(env: &Env, utility_id: u64, amount: i128) {
+    let available = balance(env, utility_id);
+    if amount > available {
+        panic_with_error!(env, RewardError::InsufficientEscrow)
+    }
+    set_balance(env, utility_id, available - amount);
+}
+
+/// Pays `amount` of `token_address` out of the pool of `utility_id`.
+pub fn release(env: &Env, utility_id: u64, token_address: &Address, to: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    debit(env, utility_id, amount);
+    token::TokenClient::new(env, token_address).transfer(
+        &env.current_contract_address(),
+        to,
+        &amount,
+    );
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Reward tokens still escrowed for the utility, including vesting
+    /// grants not yet withdrawn.
+    pub fn get_reward_escrow(env: Env, utility_id: u64) -> i128 {
+        balance(&env, utility_id)
+    }
+
+    /// Returns the escrow of a cancelled or expired utility to its
+    /// provider, less what vesting grants still owe, and closes its reward
+    /// pool. Returns the amount reclaimed.
+    pub fn reclaim_reward_escrow(env: Env, utility_id: u64, sender: Address) -> i128 {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        let closed = u.cancelled_at > 0
+            || u.raffle.cancelled
+            || (u.selection != Selection::Raffle && env.ledger().timestamp() > u.offer_expiry);
+        if !closed {
+            panic_with_error!(&env, UtilityError::ClaimWindowOpen)
+        }
+
+        let amount = balance(&env, utility_id) - vesting::outstanding(&env, utility_id);
+        if amount <= 0 {
+            panic_with_error!(&env, RewardError::NothingToWithdraw)
+        }
+        let token_address = reward_token(&env, &u);
+        release(&env, utility_id, &token_address, &u.provider, amount);
+
+        u.reward.total_amount = 0;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::reward_reclaimed(&env, utility_id, token_address, amount, u.provider);
+        amount
+    }
+}
@@ -0,0 +1,1091 @@
+//! Event schema emitted by the utility contract.
+//!
+//! Every event is published with the topics
+//! `(namespace, action, EVENT_VERSION, utility_id)` and one of the payload
+//! structs below as its data. Indexers should check the version topic before
+//! decoding a payload; it is bumped whenever a payload layout changes.
+//!
+//! Contract-wide events that do not concern a single utility use the topics
+//! `(namespace, action, EVENT_VERSION)`. Points catalog events carry the
+//! catalog item id in place of the utility id.
+
+use soroban_sdk::{
+    contracttype, symbol_short, Address, BytesN, Env, IntoVal, String, Symbol, Val, Vec,
+};
+
+use crate::structs::{ConfigUpdate, Delegation, Selection, TokenUtility, TransferPolicy, Utility};
+
+pub const EVENT_VERSION: u32 = 2;
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityCreated {
+    pub provider: Address,
+    pub partner: Address,
+    pub utility_uri: String,
+    pub offer_expiry: u64,
+    pub selection: Selection,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityUpdated {
+    pub utility_uri: String,
+    pub offer_expiry: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityPaused {
+    pub paused: bool,
+    pub by: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityCancelled {
+    pub by: Address,
+}
+
+/// Both amounts are zero once the award is removed.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointsAwardSet {
+    pub per_redemption: i128,
+    pub per_entry: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointsAwarded {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogItemListed {
+    pub provider: Address,
+    pub price: i128,
+    pub stock: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogPriceChanged {
+    pub old_price: i128,
+    pub new_price: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogStockSet {
+    pub old_stock: u32,
+    pub new_stock: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogItemRedeemed {
+    pub user: Address,
+    pub price: i128,
+    pub remaining: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CouponsAdded {
+    pub count: u32,
+    pub total: u32,
+}
+
+/// Identifies the assigned code by its position in the pool; its
+/// commitment is returned by `get_coupon_codes`.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CouponAssigned {
+    pub user: Address,
+    pub index: u32,
+    pub remaining: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimCodesAdded {
+    pub count: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimCodeUsed {
+    pub user: Address,
+    pub code_hash: BytesN<32>,
+}
+
+/// `None` once receipts are turned off.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptNftSet {
+    pub receipt_nft: Option<Address>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptIssued {
+    pub user: Address,
+    pub receipt_nft: Address,
+    pub token_id: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeesAccrued {
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeesWithdrawn {
+    pub token: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartnerPaid {
+    pub partner: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// A zero `window` means the rate limit was removed.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitSet {
+    pub window: u64,
+    pub max_per_window: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartnerShareSet {
+    pub bps: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettlementUpdated {
+    pub token: Address,
+    pub amount_per_redemption: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettlementWithdrawn {
+    pub to: Address,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettlementFunded {
+    pub from: Address,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartnerSettled {
+    pub partner: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardPriced {
+    pub price: i128,
+    pub amount: i128,
+}
+
+/// `oracle` is `None`, and the amounts zero, when the reward goes back to
+/// fixed token amounts.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardPricingSet {
+    pub oracle: Option<Address>,
+    pub reference_amount: i128,
+    pub max_payout: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardReclaimed {
+    pub token: Address,
+    pub amount: i128,
+    pub to: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VestedWithdrawn {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VestingClawedBack {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EligibleMarked {
+    pub user: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleJoined {
+    pub user: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleEnded {
+    pub ended_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleRerolled {
+    pub lapsed: Vec<Address>,
+    pub winners: Vec<Address>,
+    pub claim_deadline: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrizesSwept {
+    pub lapsed: u32,
+    pub amount: i128,
+    pub to: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleCancelled {
+    pub entrants: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicketsBought {
+    pub user: Address,
+    pub count: u64,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProceedsSettled {
+    pub token: Address,
+    pub provider_amount: i128,
+    pub platform_amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefundClaimed {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleDrawn {
+    pub seed: BytesN<32>,
+    pub total_weight: u64,
+    pub winners: Vec<Address>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardClaimed {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityClaimed {
+    pub token: Address,
+    pub user: Address,
+    pub expiry: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityRedeemed {
+    pub token: Address,
+    pub user: Address,
+    pub usage_left: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityTransferred {
+    pub token: Address,
+    pub from: Address,
+    pub to: Address,
+    pub policy: TransferPolicy,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelegationCreated {
+    pub token: Address,
+    pub owner: Address,
+    pub delegate: Address,
+    pub expires_at: u64,
+    pub max_uses: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelegationRevoked {
+    pub token: Address,
+    pub delegate: Address,
+    pub by: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EligibilityRuleSet {
+    pub enabled: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HolderRegistered {
+    pub token: Address,
+    pub holder: Address,
+    pub since: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigUpdated {
+    pub update: ConfigUpdate,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProviderAllowed {
+    pub provider: Address,
+    pub allowed: bool,
+}
+
+fn publish_global<D: IntoVal<Env, Val>>(env: &Env, namespace: Symbol, action: Symbol, data: D) {
+    env.events()
+        .publish((namespace, action, EVENT_VERSION), data);
+}
+
+fn publish<D: IntoVal<Env, Val>>(
+    env: &Env,
+    namespace: Symbol,
+    action: Symbol,
+    utility_id: u64,
+    data: D,
+) {
+    env.events()
+        .publish((namespace, action, EVENT_VERSION, utility_id), data);
+}
+
+pub fn utility_created(env: &Env, utility_id: u64, u: &Utility) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("created"),
+        utility_id,
+        UtilityCreated {
+            provider: u.provider.clone(),
+            partner: u.partner.clone(),
+            utility_uri: u.utility_uri.clone(),
+            offer_expiry: u.offer_expiry,
+            selection: u.selection,
+        },
+    );
+}
+
+pub fn utility_updated(env: &Env, utility_id: u64, u: &Utility) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("updated"),
+        utility_id,
+        UtilityUpdated {
+            utility_uri: u.utility_uri.clone(),
+            offer_expiry: u.offer_expiry,
+        },
+    );
+}
+
+pub fn utility_paused(env: &Env, utility_id: u64, paused: bool, by: Address) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("paused"),
+        utility_id,
+        UtilityPaused { paused, by },
+    );
+}
+
+pub fn utility_cancelled(env: &Env, utility_id: u64, by: Address) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("cancelled"),
+        utility_id,
+        UtilityCancelled { by },
+    );
+}
+
+pub fn vested_withdrawn(env: &Env, utility_id: u64, user: Address, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("vesting"),
+        symbol_short!("withdrawn"),
+        utility_id,
+        VestedWithdrawn {
+            user,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn vesting_clawed_back(
+    env: &Env,
+    utility_id: u64,
+    user: Address,
+    token: Address,
+    amount: i128,
+) {
+    publish(
+        env,
+        symbol_short!("vesting"),
+        symbol_short!("clawback"),
+        utility_id,
+        VestingClawedBack {
+            user,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn eligible_marked(env: &Env, utility_id: u64, user: Address) {
+    publish(
+        env,
+        symbol_short!("eligible"),
+        symbol_short!("marked"),
+        utility_id,
+        EligibleMarked { user },
+    );
+}
+
+pub fn raffle_joined(env: &Env, utility_id: u64, user: Address) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("joined"),
+        utility_id,
+        RaffleJoined { user },
+    );
+}
+
+pub fn raffle_ended(env: &Env, utility_id: u64) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("ended"),
+        utility_id,
+        RaffleEnded {
+            ended_at: env.ledger().timestamp(),
+        },
+    );
+}
+
+pub fn raffle_drawn(
+    env: &Env,
+    utility_id: u64,
+    seed: BytesN<32>,
+    total_weight: u64,
+    winners: Vec<Address>,
+) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("drawn"),
+        utility_id,
+        RaffleDrawn {
+            seed,
+            total_weight,
+            winners,
+        },
+    );
+}
+
+pub fn raffle_rerolled(
+    env: &Env,
+    utility_id: u64,
+    lapsed: Vec<Address>,
+    winners: Vec<Address>,
+    claim_deadline: u64,
+) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("rerolled"),
+        utility_id,
+        RaffleRerolled {
+            lapsed,
+            winners,
+            claim_deadline,
+        },
+    );
+}
+
+pub fn prizes_swept(env: &Env, utility_id: u64, lapsed: u32, amount: i128, to: Address) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("swept"),
+        utility_id,
+        PrizesSwept { lapsed, amount, to },
+    );
+}
+
+pub fn raffle_cancelled(env: &Env, utility_id: u64, entrants: u32) {
+    publish(
+        env,
+        symbol_short!("raffle"),
+        symbol_short!("cancelled"),
+        utility_id,
+        RaffleCancelled { entrants },
+    );
+}
+
+pub fn tickets_bought(env: &Env, utility_id: u64, user: Address, count: u64, amount: i128) {
+    publish(
+        env,
+        symbol_short!("tickets"),
+        symbol_short!("bought"),
+        utility_id,
+        TicketsBought {
+            user,
+            count,
+            amount,
+        },
+    );
+}
+
+pub fn proceeds_settled(
+    env: &Env,
+    utility_id: u64,
+    token: Address,
+    provider_amount: i128,
+    platform_amount: i128,
+) {
+    publish(
+        env,
+        symbol_short!("tickets"),
+        symbol_short!("settled"),
+        utility_id,
+        ProceedsSettled {
+            token,
+            provider_amount,
+            platform_amount,
+        },
+    );
+}
+
+pub fn refund_claimed(env: &Env, utility_id: u64, user: Address, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("tickets"),
+        symbol_short!("refunded"),
+        utility_id,
+        RefundClaimed {
+            user,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn reward_claimed(env: &Env, utility_id: u64, user: Address, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("reward"),
+        symbol_short!("claimed"),
+        utility_id,
+        RewardClaimed {
+            user,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn utility_claimed(env: &Env, utility_id: u64, token: Address, user: Address, expiry: u64) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("claimed"),
+        utility_id,
+        UtilityClaimed {
+            token,
+            user,
+            expiry,
+        },
+    );
+}
+
+pub fn utility_redeemed(
+    env: &Env,
+    utility_id: u64,
+    token: Address,
+    user: Address,
+    usage_left: u64,
+) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("redeemed"),
+        utility_id,
+        UtilityRedeemed {
+            token,
+            user,
+            usage_left,
+        },
+    );
+}
+
+pub fn config_updated(env: &Env, update: ConfigUpdate) {
+    publish_global(
+        env,
+        symbol_short!("config"),
+        symbol_short!("updated"),
+        ConfigUpdated { update },
+    );
+}
+
+pub fn provider_allowed(env: &Env, provider: Address, allowed: bool) {
+    publish_global(
+        env,
+        symbol_short!("provider"),
+        symbol_short!("allowed"),
+        ProviderAllowed { provider, allowed },
+    );
+}
+
+pub fn delegation_created(env: &Env, d: &Delegation) {
+    publish(
+        env,
+        symbol_short!("delegate"),
+        symbol_short!("created"),
+        d.utility_id,
+        DelegationCreated {
+            token: d.token.clone(),
+            owner: d.owner.clone(),
+            delegate: d.delegate.clone(),
+            expires_at: d.expires_at,
+            max_uses: d.max_uses,
+        },
+    );
+}
+
+pub fn delegation_revoked(env: &Env, d: &Delegation, by: Address) {
+    publish(
+        env,
+        symbol_short!("delegate"),
+        symbol_short!("revoked"),
+        d.utility_id,
+        DelegationRevoked {
+            token: d.token.clone(),
+            delegate: d.delegate.clone(),
+            by,
+        },
+    );
+}
+
+pub fn utility_transferred(
+    env: &Env,
+    token_utility: &TokenUtility,
+    from: Address,
+    policy: TransferPolicy,
+) {
+    publish(
+        env,
+        symbol_short!("utility"),
+        symbol_short!("transfer"),
+        token_utility.utility_id,
+        UtilityTransferred {
+            token: token_utility.token.clone(),
+            from,
+            to: token_utility.owner.clone(),
+            policy,
+        },
+    );
+}
+
+pub fn holder_registered(env: &Env, token: Address, holder: Address, since: u64) {
+    publish_global(
+        env,
+        symbol_short!("holder"),
+        symbol_short!("register"),
+        HolderRegistered {
+            token,
+            holder,
+            since,
+        },
+    );
+}
+
+pub fn eligibility_rule_set(env: &Env, utility_id: u64, enabled: bool) {
+    publish(
+        env,
+        symbol_short!("rule"),
+        symbol_short!("set"),
+        utility_id,
+        EligibilityRuleSet { enabled },
+    );
+}
+
+pub fn points_award_set(env: &Env, utility_id: u64, per_redemption: i128, per_entry: i128) {
+    publish(
+        env,
+        symbol_short!("points"),
+        symbol_short!("set"),
+        utility_id,
+        PointsAwardSet {
+            per_redemption,
+            per_entry,
+        },
+    );
+}
+
+pub fn points_awarded(env: &Env, utility_id: u64, user: Address, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("points"),
+        symbol_short!("awarded"),
+        utility_id,
+        PointsAwarded {
+            user,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn catalog_item_listed(env: &Env, item_id: u64, provider: Address, price: i128, stock: u32) {
+    publish(
+        env,
+        symbol_short!("catalog"),
+        symbol_short!("listed"),
+        item_id,
+        CatalogItemListed {
+            provider,
+            price,
+            stock,
+        },
+    );
+}
+
+pub fn catalog_price_changed(env: &Env, item_id: u64, old_price: i128, new_price: i128) {
+    publish(
+        env,
+        symbol_short!("catalog"),
+        symbol_short!("price"),
+        item_id,
+        CatalogPriceChanged {
+            old_price,
+            new_price,
+        },
+    );
+}
+
+pub fn catalog_stock_set(env: &Env, item_id: u64, old_stock: u32, new_stock: u32) {
+    publish(
+        env,
+        symbol_short!("catalog"),
+        symbol_short!("stock"),
+        item_id,
+        CatalogStockSet {
+            old_stock,
+            new_stock,
+        },
+    );
+}
+
+pub fn catalog_item_redeemed(env: &Env, item_id: u64, user: Address, price: i128, remaining: u32) {
+    publish(
+        env,
+        symbol_short!("catalog"),
+        symbol_short!("redeemed"),
+        item_id,
+        CatalogItemRedeemed {
+            user,
+            price,
+            remaining,
+        },
+    );
+}
+
+pub fn coupons_added(env: &Env, utility_id: u64, count: u32, total: u32) {
+    publish(
+        env,
+        symbol_short!("coupon"),
+        symbol_short!("added"),
+        utility_id,
+        CouponsAdded { count, total },
+    );
+}
+
+pub fn coupon_assigned(env: &Env, utility_id: u64, user: Address, index: u32, remaining: u32) {
+    publish(
+        env,
+        symbol_short!("coupon"),
+        symbol_short!("assigned"),
+        utility_id,
+        CouponAssigned {
+            user,
+            index,
+            remaining,
+        },
+    );
+}
+
+pub fn claim_codes_added(env: &Env, utility_id: u64, count: u32) {
+    publish(
+        env,
+        symbol_short!("code"),
+        symbol_short!("added"),
+        utility_id,
+        ClaimCodesAdded { count },
+    );
+}
+
+pub fn claim_code_used(env: &Env, utility_id: u64, user: Address, code_hash: BytesN<32>) {
+    publish(
+        env,
+        symbol_short!("code"),
+        symbol_short!("used"),
+        utility_id,
+        ClaimCodeUsed { user, code_hash },
+    );
+}
+
+pub fn receipt_nft_set(env: &Env, utility_id: u64, receipt_nft: Option<Address>) {
+    publish(
+        env,
+        symbol_short!("receipt"),
+        symbol_short!("set"),
+        utility_id,
+        ReceiptNftSet { receipt_nft },
+    );
+}
+
+pub fn receipt_issued(
+    env: &Env,
+    utility_id: u64,
+    user: Address,
+    receipt_nft: Address,
+    token_id: u64,
+) {
+    publish(
+        env,
+        symbol_short!("receipt"),
+        symbol_short!("issued"),
+        utility_id,
+        ReceiptIssued {
+            user,
+            receipt_nft,
+            token_id,
+        },
+    );
+}
+
+pub fn fees_accrued(env: &Env, utility_id: u64, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("fee"),
+        symbol_short!("accrued"),
+        utility_id,
+        FeesAccrued { token, amount },
+    );
+}
+
+pub fn fees_withdrawn(env: &Env, token: Address, to: Address, amount: i128) {
+    publish_global(
+        env,
+        symbol_short!("fee"),
+        symbol_short!("withdrawn"),
+        FeesWithdrawn { token, to, amount },
+    );
+}
+
+pub fn partner_paid(env: &Env, utility_id: u64, partner: Address, token: Address, amount: i128) {
+    publish(
+        env,
+        symbol_short!("partner"),
+        symbol_short!("paid"),
+        utility_id,
+        PartnerPaid {
+            partner,
+            token,
+            amount,
+        },
+    );
+}
+
+pub fn rate_limit_set(env: &Env, utility_id: u64, window: u64, max_per_window: u32) {
+    publish(
+        env,
+        symbol_short!("giveaway"),
+        symbol_short!("ratelimit"),
+        utility_id,
+        RateLimitSet {
+            window,
+            max_per_window,
+        },
+    );
+}
+
+pub fn partner_share_set(env: &Env, utility_id: u64, bps: u32) {
+    publish(
+        env,
+        symbol_short!("partner"),
+        symbol_short!("share"),
+        utility_id,
+        PartnerShareSet { bps },
+    );
+}
+
+pub fn settlement_updated(env: &Env, utility_id: u64, token: Address, amount_per_redemption: i128) {
+    publish(
+        env,
+        symbol_short!("settle"),
+        symbol_short!("updated"),
+        utility_id,
+        SettlementUpdated {
+            token,
+            amount_per_redemption,
+        },
+    );
+}
+
+pub fn settlement_withdrawn(env: &Env, utility_id: u64, to: Address, amount: i128, balance: i128) {
+    publish(
+        env,
+        symbol_short!("settle"),
+        symbol_short!("withdrawn"),
+        utility_id,
+        SettlementWithdrawn {
+            to,
+            amount,
+            balance,
+        },
+    );
+}
+
+pub fn settlement_funded(env: &Env, utility_id: u64, from: Address, amount: i128, balance: i128) {
+    publish(
+        env,
+        symbol_short!("settle"),
+        symbol_short!("funded"),
+        utility_id,
+        SettlementFunded {
+            from,
+            amount,
+            balance,
+        },
+    );
+}
+
+pub fn partner_settled(
+    env: &Env,
+    utility_id: u64,
+    partner: Address,
+    token: Address,
+    amount: i128,
+    balance: i128,
+) {
+    publish(
+        env,
+        symbol_short!("settle"),
+        symbol_short!("paid"),
+        utility_id,
+        PartnerSettled {
+            partner,
+            token,
+            amount,
+            balance,
+        },
+    );
+}
+
+pub fn reward_priced(env: &Env, utility_id: u64, price: i128, amount: i128) {
+    publish(
+        env,
+        symbol_short!("reward"),
+        symbol_short!("priced"),
+        utility_id,
+        RewardPriced { price, amount },
+    );
+}
+
+pub fn reward_pricing_set(
+    env: &Env,
+    utility_id: u64,
+    oracle: Option<Address>,
+    reference_amount: i128,
+    max_payout: i128,
+) {
+    publish(
+        env,
+        symbol_short!("reward"),
+        symbol_short!("pricing"),
+        utility_id,
+        RewardPricingSet {
+            oracle,
+            reference_amount,
+            max_payout,
+        },
+    );
+}
+
+pub fn reward_reclaimed(env: &Env, utility_id: u64, token: Address, amount: i128, to: Address) {
+    publish(
+        env,
+        symbol_short!("reward"),
+        symbol_short!("reclaimed"),
+        utility_id,
+        RewardReclaimed { token, amount, to },
+    );
+}
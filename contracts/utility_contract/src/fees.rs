@@ -0,0 +1,143 @@
+//! Platform fees and revenue splits.
+//!
+//! The platform charges `creation_fee` of `fee_token` for every new
+//! utility, and skims `platform_fee_bps` off rewards paid out of escrow and
+//! off ticket sale proceeds. Fees stay in the contract, accounted per token
+//! in a fee vault the admin withdraws from. A provider may also share part
+//! of its ticket proceeds with the utility's partner.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol};
+
+use crate::structs::{RewardError, Utility, UtilityError};
+use crate::{config, events, UtilityContract, UtilityContractClient};
+
+pub const VAULT: Symbol = symbol_short!("VAULT");
+pub const PARTNER_SHARE: Symbol = symbol_short!("PARTNER");
+
+pub const BPS_DENOMINATOR: i128 = 10_000;
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn bps_of(env: &Env, amount: i128, bps: u32) -> i128 {
+    amount
+        .checked_mul(bps as i128)
+        .map(|v| v / BPS_DENOMINATOR)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow))
+}
+
+/// The platform fee on `amount`, or 0 before the contract is set up.
+pub fn platform_fee(env: &Env, amount: i128) -> i128 {
+    config::load(env).map_or(0, |c| bps_of(env, amount, c.platform_fee_bps))
+}
+
+pub fn vault_balance(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address), i128>(&(VAULT, token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn set_vault_balance(env: &Env, token_address: &Address, amount: i128) {
+    let key = (VAULT, token_address.clone());
+    env.storage().persistent().set(&key, &amount);
+    config::extend_persistent(env, &key);
+}
+
+/// Credits `amount` of `token_address`, already held by the contract, to
+/// the fee vault.
+pub fn accrue(env: &Env, utility_id: u64, token_address: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    let balance = vault_balance(env, token_address)
+        .checked_add(amount)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    set_vault_balance(env, token_address, balance);
+
+    events::fees_accrued(env, utility_id, token_address.clone(), amount);
+}
+
+pub fn partner_share(env: &Env, utility_id: u64) -> u32 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), u32>(&(PARTNER_SHARE, utility_id))
+        .unwrap_or(0)
+}
+
+/// Charges the configured creation fee to the provider of `u`. Nothing is
+/// charged until a fee token is configured.
+pub fn charge_creation(env: &Env, u: &Utility) {
+    let Some(c) = config::load(env) else {
+        return;
+    };
+    let Some(fee_token) = c.fee_token else {
+        return;
+    };
+    if c.creation_fee == 0 {
+        return;
+    }
+
+    u.provider.require_auth();
+    token::TokenClient::new(env, &fee_token).transfer(
+        &u.provider,
+        &env.current_contract_address(),
+        &c.creation_fee,
+    );
+    accrue(env, u.id, &fee_token, c.creation_fee);
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Pays `bps` basis points of the provider's ticket proceeds to the
+    /// utility's partner.
+    pub fn set_partner_share(env: Env, utility_id: u64, sender: Address, bps: u32) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if bps as i128 > BPS_DENOMINATOR {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        let key = (PARTNER_SHARE, utility_id);
+        env.storage().persistent().set(&key, &bps);
+        config::extend_persistent(&env, &key);
+
+        events::partner_share_set(&env, utility_id, bps);
+    }
+
+    pub fn get_partner_share(env: Env, utility_id: u64) -> u32 {
+        partner_share(&env, utility_id)
+    }
+
+    pub fn get_fee_balance(env: Env, token_address: Address) -> i128 {
+        vault_balance(&env, &token_address)
+    }
+
+    /// Sends `amount` of accrued `token_address` fees to `to`. Only the
+    /// admin may withdraw.
+    pub fn withdraw_fees(
+        env: Env,
+        sender: Address,
+        token_address: Address,
+        to: Address,
+        amount: i128,
+    ) {
+        sender.require_auth();
+        if sender != config::read(&env).admin {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        let balance = vault_balance(&env, &token_address);
+        if amount <= 0 || amount > balance {
+            panic_with_error!(&env, RewardError::InsufficientFees)
+        }
+
+        set_vault_balance(&env, &token_address, balance - amount);
+        token::TokenClient::new(&env, &token_address).transfer(
+            &env.current_contract_address(),
+            &to,
+            &amount,
+        );
+
+        events::fees_withdrawn(&env, token_address, to, amount);
+    }
+}
@@ -0,0 +1,126 @@
+//! First-come-first-served giveaways.
+//!
+//! A `Giveaway` utility has no raffle phase: any eligible user may claim
+//! their reward directly until `no_of_winners` claims have been made. An
+//! optional rate limit caps claims per fixed time window, counted in
+//! buckets of `now / window`, so a drop cannot be drained in one ledger.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, Address, Env, Symbol};
+
+use crate::structs::{RateLimit, Selection, Utility, UtilityError, UtilityType};
+use crate::{config, events, storage, UtilityContract, UtilityContractClient};
+
+pub const CLAIM_COUNT: Symbol = symbol_short!("CLAIM_CNT");
+pub const RATE_LIMIT: Symbol = symbol_short!("RATE");
+pub const BUCKET: Symbol = symbol_short!("BUCKET");
+
+pub fn claim_count(env: &Env, utility_id: u64) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), u64>(&(CLAIM_COUNT, utility_id))
+        .unwrap_or(0)
+}
+
+pub fn rate_limit(env: &Env, utility_id: u64) -> Option<RateLimit> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), RateLimit>(&(RATE_LIMIT, utility_id))
+}
+
+/// Counts one giveaway claim against the winner cap and the rate limit.
+pub fn record_claim(env: &Env, u: &Utility) {
+    let claimed = claim_count(env, u.id);
+    if claimed >= u.reward.no_of_winners {
+        panic_with_error!(env, UtilityError::AllRewardsClaimed)
+    }
+
+    if let Some(limit) = rate_limit(env, u.id) {
+        let bucket = env.ledger().timestamp() / limit.window;
+        let key = (BUCKET, u.id);
+        let in_bucket = match env.storage().persistent().get::<_, (u64, u32)>(&key) {
+            Some((b, count)) if b == bucket => count,
+            _ => 0,
+        };
+        if in_bucket >= limit.max_per_window {
+            panic_with_error!(env, UtilityError::RateLimited)
+        }
+        env.storage()
+            .persistent()
+            .set(&key, &(bucket, in_bucket + 1));
+        config::extend_persistent(env, &key);
+    }
+
+    let key = (CLAIM_COUNT, u.id);
+    env.storage().persistent().set(&key, &(claimed + 1));
+    config::extend_persistent(env, &key);
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Switches a utility between NFT benefit and giveaway behaviour.
+    /// Giveaways cannot be raffles.
+    pub fn set_utility_type(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        utility_type: UtilityType,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if utility_type == UtilityType::Giveaway && u.selection != Selection::All {
+            panic_with_error!(&env, UtilityError::InvalidSelection)
+        }
+
+        u.utility_type = utility_type;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Allows at most `max_per_window` giveaway claims every `window`
+    /// seconds. A `window` of 0 removes the limit.
+    pub fn set_claim_rate_limit(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        window: u64,
+        max_per_window: u32,
+    ) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        let key = (RATE_LIMIT, utility_id);
+        if window == 0 {
+            env.storage().persistent().remove(&key);
+        } else {
+            if max_per_window == 0 {
+                panic_with_error!(&env, UtilityError::InvalidConfig)
+            }
+            env.storage().persistent().set(
+                &key,
+                &RateLimit {
+                    window,
+                    max_per_window,
+                },
+            );
+            config::extend_persistent(&env, &key);
+        }
+
+        events::rate_limit_set(&env, utility_id, window, max_per_window);
+    }
+
+    pub fn get_claim_rate_limit(env: Env, utility_id: u64) -> Option<RateLimit> {
+        rate_limit(&env, utility_id)
+    }
+
+    pub fn get_giveaway_claims(env: Env, utility_id: u64) -> u64 {
+        claim_count(&env, utility_id)
+    }
+}
@@ -0,0 +1,133 @@
+//! Minimum holding period gating.
+//!
+//! Holders register themselves once per NFT through `register_holder`, which
+//! records the first time the contract saw them holding it. Utilities with a
+//! non-zero `min_holding_period` only admit holders whose record is at least
+//! that old and who still hold at least the balance they registered with.
+//! A holder whose balance dropped has to register again, which restarts
+//! the clock.
+//!
+//! Records are dropped when the NFT contract reports a transfer away from
+//! the holder through `on_nft_transfer`. A sale followed by a re-buy that
+//! leaves the balance unchanged is only caught by that hook, so
+//! collections gated by a holding period need to call it on every
+//! transfer.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol, Vec};
+
+use crate::structs::{HolderRecord, Utility, UtilityError};
+use crate::{config, events, storage, UtilityContract, UtilityContractClient};
+
+pub const HOLDER: Symbol = symbol_short!("HOLDER");
+
+fn holder_record(env: &Env, token: &Address, holder: &Address) -> Option<HolderRecord> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address, Address), HolderRecord>(&(HOLDER, token.clone(), holder.clone()))
+}
+
+pub fn holder_since(env: &Env, token: &Address, holder: &Address) -> Option<u64> {
+    holder_record(env, token, holder).map(|r| r.since)
+}
+
+pub fn forget_holder(env: &Env, token: &Address, holder: &Address) {
+    env.storage()
+        .persistent()
+        .remove(&(HOLDER, token.clone(), holder.clone()));
+}
+
+/// Whether `holder` still holds the balance of `token` they registered
+/// with, and registered at least `period` seconds ago.
+pub fn has_held_for(env: &Env, token: &Address, holder: &Address, period: u64) -> bool {
+    let Some(record) = holder_record(env, token, holder) else {
+        return false;
+    };
+    let balance = token::TokenClient::new(env, token).balance(holder);
+    balance > 0
+        && balance >= record.balance
+        && env.ledger().timestamp() >= record.since.saturating_add(period)
+}
+
+/// Enforces the utility's holding period for `holder`. With `token` set
+/// only that NFT counts; otherwise any of the utility's collections does.
+pub fn require_held(env: &Env, u: &Utility, token: Option<&Address>, holder: &Address) {
+    if u.min_holding_period == 0 {
+        return;
+    }
+
+    let held = match token {
+        Some(token) => has_held_for(env, token, holder, u.min_holding_period),
+        None => u
+            .collections
+            .iter()
+            .any(|c| has_held_for(env, &c, holder, u.min_holding_period)),
+    };
+    if !held {
+        panic_with_error!(env, UtilityError::HoldingPeriodNotMet)
+    }
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Records that `holder` holds `token_address` as of now. Calling it
+    /// again keeps the original timestamp unless the holder's balance has
+    /// dropped since they registered.
+    pub fn register_holder(env: Env, token_address: Address, holder: Address) -> u64 {
+        let balance = token::TokenClient::new(&env, &token_address).balance(&holder);
+        if balance <= 0 {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        let key = (HOLDER, token_address.clone(), holder.clone());
+        if let Some(record) = holder_record(&env, &token_address, &holder) {
+            if balance >= record.balance {
+                config::extend_persistent(&env, &key);
+                return record.since;
+            }
+        }
+
+        let since = env.ledger().timestamp();
+        env.storage()
+            .persistent()
+            .set(&key, &HolderRecord { since, balance });
+        config::extend_persistent(&env, &key);
+
+        events::holder_registered(&env, token_address, holder, since);
+        since
+    }
+
+    pub fn get_holder_since(env: Env, token_address: Address, holder: Address) -> Option<u64> {
+        holder_since(&env, &token_address, &holder)
+    }
+
+    /// Requires holders to have held one of `collections` for
+    /// `min_holding_period` seconds before joining, claiming or redeeming.
+    /// A non-zero period needs at least one collection.
+    pub fn set_holding_requirement(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        collections: Vec<Address>,
+        min_holding_period: u64,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if collections.len() > config::max_batch_size(&env) {
+            panic_with_error!(&env, UtilityError::BatchTooLarge)
+        }
+        if min_holding_period > 0 && collections.is_empty() {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        u.collections = collections;
+        u.min_holding_period = min_holding_period;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+}
@@ -1,17 +1,42 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, panic_with_error, symbol_short, token, Address, Env, Map, String,
-    Symbol, Vec,
+    contract, contractimpl, panic_with_error, symbol_short, token, Address, BytesN, Env, IntoVal,
+    String, Symbol, TryFromVal, Val, Vec,
 };
+pub mod catalog;
+pub mod claim_codes;
+pub mod config;
+pub mod coupons;
+pub mod delegation;
+pub mod eligibility;
+pub mod escrow;
+pub mod events;
+pub mod fees;
+pub mod giveaway;
+pub mod holding;
+pub mod points;
+pub mod pricing;
+pub mod raffle;
+pub mod receipts;
+pub mod settlement;
+pub mod storage;
 pub mod structs;
+pub mod tickets;
+pub mod transfer;
+pub mod vesting;
+
+use structs::{
+    Config, ConfigUpdate, EntryWeight, ExpiryType, Raffle, RaffleStatus, Receipt, Reward,
+    RewardError, Selection, StatusReason, TokenUtility, TransferPolicy, UsageType, UserBenefit,
+    Utility, UtilityError, UtilityPage, UtilityStatusReport, UtilityType, Vesting,
+};
 
-use structs::{ExpiryType, Raffle, Receipt, Reward, Selection, UsageType, Utility, UtilityError};
-
-const ADMIN: Symbol = symbol_short!("ADMIN");
-const U_STORAGE: Symbol = symbol_short!("U_STORAGE");
 const ELIGIBLE: Symbol = symbol_short!("ELIGIBLE");
-const TOKEN_UTILITY: Symbol = symbol_short!("TOKEN_U");
+const CLAIMED: Symbol = symbol_short!("claimed");
+
+/// Upper bound on the number of utilities inspected by a filtered list call.
+const MAX_SCAN: u64 = 200;
 
 mod test;
 
@@ -20,28 +45,97 @@ pub struct UtilityContract;
 
 #[contractimpl]
 impl UtilityContract {
-    pub fn setup_config(env: Env, admin: String) -> String {
-        let admin_address = Address::from_string(&admin);
-        env.storage().persistent().set(&ADMIN, &admin_address);
-        let utilities: Vec<Utility> = Vec::new(&env);
-        env.storage().persistent().set(&U_STORAGE, &utilities);
-        admin
+    pub fn setup_config(env: Env, admin: Address) -> Config {
+        if config::load(&env).is_some() {
+            panic_with_error!(&env, UtilityError::AlreadyInitialized)
+        }
+        admin.require_auth();
+
+        let c = config::defaults(admin);
+        config::save(&env, &c);
+        c
     }
 
-    pub fn get_config(env: Env) -> String {
-        let addr = env.storage().persistent().get::<Symbol, String>(&ADMIN);
+    pub fn get_config(env: Env) -> Config {
+        config::read(&env)
+    }
 
-        match addr {
-            Some(a) => a,
-            None => String::from_str(&env, &"No Admin Set"),
+    /// Applies a single field change to the config. Only the current admin
+    /// may call this.
+    pub fn set_config(env: Env, sender: Address, update: ConfigUpdate) -> Config {
+        sender.require_auth();
+        let mut c = config::read(&env);
+        if sender != c.admin {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
         }
+
+        match update.clone() {
+            ConfigUpdate::Admin(admin) => c.admin = admin,
+            ConfigUpdate::FeeToken(token) => c.fee_token = Some(token),
+            ConfigUpdate::CreationFee(fee) => {
+                if fee < 0 {
+                    panic_with_error!(&env, UtilityError::InvalidConfig)
+                }
+                c.creation_fee = fee;
+            }
+            ConfigUpdate::PlatformFeeBps(bps) => {
+                if bps > 10_000 {
+                    panic_with_error!(&env, UtilityError::InvalidConfig)
+                }
+                c.platform_fee_bps = bps;
+            }
+            ConfigUpdate::Ttl(threshold, extend_to) => {
+                if threshold > extend_to || extend_to > env.storage().max_ttl() {
+                    panic_with_error!(&env, UtilityError::InvalidConfig)
+                }
+                c.ttl_threshold = threshold;
+                c.ttl_extend_to = extend_to;
+            }
+            ConfigUpdate::MaxPageSize(size) => {
+                if size == 0 {
+                    panic_with_error!(&env, UtilityError::InvalidConfig)
+                }
+                c.max_page_size = size;
+            }
+            ConfigUpdate::MaxBatchSize(size) => {
+                if size == 0 {
+                    panic_with_error!(&env, UtilityError::InvalidConfig)
+                }
+                c.max_batch_size = size;
+            }
+            ConfigUpdate::ProviderMode(mode) => c.provider_mode = mode,
+        }
+        config::save(&env, &c);
+
+        events::config_updated(&env, update);
+        c
+    }
+
+    /// Adds or removes `provider` from the allowlist consulted when the
+    /// config is in `ProviderMode::Allowlist`.
+    pub fn set_provider_allowed(env: Env, sender: Address, provider: Address, allowed: bool) {
+        sender.require_auth();
+        if sender != config::read(&env).admin {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        let key = (config::ALLOWED_PROVIDER, provider.clone());
+        env.storage().persistent().set(&key, &allowed);
+        config::extend_persistent(&env, &key);
+
+        events::provider_allowed(&env, provider, allowed);
+    }
+
+    pub fn is_provider_allowed(env: Env, provider: Address) -> bool {
+        config::is_provider_allowed(&env, &provider)
     }
 
     pub fn check_nft_ownership(env: Env, token_address: Address, user: Address) -> bool {
-        let token_client = token::StellarAssetClient::new(&env, &token_address);
-        token_client.authorized(&user)
+        let token_client = token::TokenClient::new(&env, &token_address);
+        token_client.balance(&user) > 0
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_new_utility(
         env: Env,
         address_strings: Vec<String>,
@@ -52,90 +146,112 @@ impl UtilityContract {
         selection_u32: u32,
         reward_receipt_u32: u32,
         reward_token_addresses: Vec<Address>,
-        reward_u64s: Vec<u64>,
+        reward_amounts: Vec<i128>,
     ) -> Utility {
+        let receipt = match reward_receipt_u32 {
+            0 => Receipt::None,
+            1 => Receipt::MintToken,
+            2 => Receipt::External,
+            3 => Receipt::HTSToken,
+            4 => Receipt::Points,
+            _ => panic_with_error!(&env, UtilityError::InvalidReceipt),
+        };
+        let decimals = reward_decimals(&env, &receipt, &reward_token_addresses);
         let reward = Reward {
-            receipt: match reward_receipt_u32 {
-                0 => Receipt::None,
-                1 => Receipt::MintToken,
-                2 => Receipt::External,
-                3 => Receipt::HTSToken,
-                _ => panic_with_error!(&env, UtilityError::InvalidExpiry),
-            },
+            receipt,
             token_addresses: reward_token_addresses,
-            total_amount: reward_u64s.try_get(0).unwrap().unwrap(),
-            amount_per_win: reward_u64s.try_get(1).unwrap().unwrap(),
-            no_of_winners: reward_u64s.try_get(2).unwrap().unwrap(),
+            total_amount: param(&env, &reward_amounts, 0),
+            amount_per_win: param(&env, &reward_amounts, 1),
+            no_of_winners: u64::try_from(param(&env, &reward_amounts, 2))
+                .unwrap_or_else(|_| panic_with_error!(&env, RewardError::InvalidRewardAmount)),
+            decimals,
+            vesting: Vesting::None,
         };
+        validate_reward(&env, &reward);
 
-        let e: ExpiryType;
-        let usage: UsageType;
-        let selection: Selection;
-
-        match selection_u32 {
-            0 => selection = Selection::All,
-            1 => selection = Selection::Raffle,
-            _ => panic_with_error!(&env, UtilityError::InvalidExpiry),
-        }
+        let selection = match selection_u32 {
+            0 => Selection::All,
+            1 => Selection::Raffle,
+            _ => panic_with_error!(&env, UtilityError::InvalidSelection),
+        };
 
-        match usages.try_get(0).unwrap().unwrap() {
-            0 => usage = UsageType::Unlimited,
-            1 => usage = UsageType::Limited,
-            _ => panic_with_error!(&env, UtilityError::InvalidExpiry),
-        }
-        match expiries.try_get(0).unwrap().unwrap() {
-            0 => e = ExpiryType::None,
-            1 => e = ExpiryType::TimeBased,
-            2 => e = ExpiryType::DateBased,
+        let usage = match param(&env, &usages, 0) {
+            0 => UsageType::Unlimited,
+            1 => UsageType::Limited,
+            _ => panic_with_error!(&env, UtilityError::InvalidUsage),
+        };
+        let e = match param(&env, &expiries, 0) {
+            0 => ExpiryType::None,
+            1 => ExpiryType::TimeBased,
+            2 => ExpiryType::DateBased,
             _ => panic_with_error!(&env, UtilityError::InvalidExpiry),
-        }
+        };
 
+        let offer_expiry = param(&env, &expiries, 2);
         let u = Utility {
-            provider: Address::from_string(&address_strings.try_get(0).unwrap().unwrap()),
-            expiry: expiries.try_get(1).unwrap().unwrap(),
-            usage: usages.try_get(1).unwrap().unwrap(),
-            offer_expiry: expiries.try_get(2).unwrap().unwrap(),
-            partner: Address::from_string(&address_strings.try_get(1).unwrap().unwrap()),
+            id: storage::utility_count(&env),
+            provider: Address::from_string(&param(&env, &address_strings, 0)),
+            expiry: param(&env, &expiries, 1),
+            usage: param(&env, &usages, 1),
+            offer_expiry,
+            partner: Address::from_string(&param(&env, &address_strings, 1)),
             utility_uri,
             expiry_type: e,
             usage_type: usage,
             raffle: Raffle {
                 start_time: raffle_start_time,
                 ended: false,
+                weight: EntryWeight::Equal,
+                cancelled: false,
+                join_end_time: offer_expiry,
+                max_entrants: 0,
+                claim_deadline: 0,
             },
             selection,
             reward,
+            paused: false,
+            transfer_policy: TransferPolicy::CarryOver,
+            collections: Vec::new(&env),
+            min_holding_period: 0,
+            utility_type: UtilityType::NftBenefit,
+            cancelled_at: 0,
         };
 
-        if u.selection == Selection::Raffle {
-            if u.raffle.start_time < env.ledger().timestamp()
-                || u.raffle.start_time > u.offer_expiry
-            {
-                panic_with_error!(env, UtilityError::InvalidTime);
-            }
+        if !config::is_provider_allowed(&env, &u.provider) {
+            panic_with_error!(&env, UtilityError::ProviderNotAllowed);
+        }
+        fees::charge_creation(&env, &u);
+
+        if u.selection == Selection::Raffle
+            && (u.raffle.start_time < env.ledger().timestamp()
+                || u.raffle.start_time > u.offer_expiry)
+        {
+            panic_with_error!(env, UtilityError::InvalidTime);
         }
 
+        // External rewards are paid out of a pool escrowed in full up
+        // front, which the provider spends from the funding address.
         if u.reward.receipt == Receipt::External {
-            for addr in u.reward.token_addresses.clone() {
-                let token_client = token::TokenClient::new(&env, &addr);
-                u.provider.require_auth();
-                if token_client.balance(&u.provider) < 1 {
-                    panic_with_error!(&env, UtilityError::InsufficientBalance);
-                }
-                token_client.transfer_from(
-                    &u.provider,
-                    &Address::from_string(&address_strings.try_get(2).unwrap().unwrap()),
-                    &env.current_contract_address(),
-                    &(1 as i128),
-                );
+            let token_client = token::TokenClient::new(&env, &reward_token(&env, &u));
+            let funder = Address::from_string(&param(&env, &address_strings, 2));
+            u.provider.require_auth();
+            if token_client.balance(&funder) < u.reward.total_amount {
+                panic_with_error!(&env, UtilityError::InsufficientBalance);
             }
+            token_client.transfer_from(
+                &u.provider,
+                &funder,
+                &env.current_contract_address(),
+                &u.reward.total_amount,
+            );
+            escrow::deposit(&env, u.id, u.reward.total_amount);
         }
 
         if u.offer_expiry < env.ledger().timestamp() {
             panic_with_error!(&env, UtilityError::InvalidExpiry);
         }
         if u.usage_type == UsageType::Limited && u.usage < 1 {
-            panic_with_error!(&env, UtilityError::InvalidTime);
+            panic_with_error!(&env, UtilityError::InvalidUsage);
         }
 
         // fetch nft collections
@@ -143,84 +259,116 @@ impl UtilityContract {
         if (u.expiry_type == ExpiryType::TimeBased || u.expiry_type == ExpiryType::DateBased)
             && u.expiry < env.ledger().timestamp()
         {
-            panic_with_error!(&env, UtilityError::InsufficientBalance);
+            panic_with_error!(&env, UtilityError::InvalidExpiry);
         }
 
-        if env.storage().persistent().has(&U_STORAGE) {
-            let mut utilities = env
-                .storage()
-                .persistent()
-                .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-                .unwrap();
-            utilities.push_back(u.clone());
-            env.storage().persistent().set(&U_STORAGE, &utilities);
-        } else {
-            let mut utilities = Vec::new(&env);
-            utilities.push_back(u.clone());
-            env.storage().persistent().set(&U_STORAGE, &utilities);
-        }
+        storage::add_utility(&env, &u);
+
+        events::utility_created(&env, u.id, &u);
         u
     }
 
-    pub fn get_time(env: Env) -> u64 {
-        env.ledger().timestamp()
+    pub fn update_utility(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        utility_uri: String,
+        offer_expiry: u64,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        if offer_expiry < env.ledger().timestamp() {
+            panic_with_error!(&env, UtilityError::InvalidExpiry)
+        }
+
+        u.utility_uri = utility_uri;
+        u.offer_expiry = offer_expiry;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
     }
 
-    pub fn join_raffle(env: Env, utility_id: u64, sender: Address, user: Address) {
-        if env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .is_none()
-        {
-            panic_with_error!(&env, UtilityError::UtilityNotFound);
+    pub fn set_utility_paused(env: Env, utility_id: u64, sender: Address, paused: bool) {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.cancelled_at > 0 {
+            panic_with_error!(&env, UtilityError::UtilityCancelled)
         }
-        let utility = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap();
-        let u = utility.get(utility_id as u32).unwrap();
 
-        if u.provider != sender.clone() {
-            panic_with_error!(&env, UtilityError::NotAuthorized);
+        u.paused = paused;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_paused(&env, utility_id, paused, sender);
+    }
+
+    /// Permanently withdraws a utility. It stays paused, an undrawn raffle
+    /// is cancelled so tickets can be refunded, and reward vesting stops so
+    /// the provider can claw back what has not vested.
+    pub fn cancel_utility(env: Env, utility_id: u64, sender: Address) {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.cancelled_at > 0 {
+            panic_with_error!(&env, UtilityError::UtilityCancelled)
         }
 
-        if u.offer_expiry < env.ledger().timestamp() {
-            panic_with_error!(&env, UtilityError::RaffleExpired);
+        u.paused = true;
+        u.cancelled_at = env.ledger().timestamp().max(1);
+        if u.selection == Selection::Raffle && !u.raffle.ended {
+            u.raffle.ended = true;
+            u.raffle.cancelled = true;
         }
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_cancelled(&env, utility_id, sender);
+    }
 
-        if u.selection != Selection::Raffle || u.raffle.ended == true {
-            panic_with_error!(&env, UtilityError::InvalidRaffleSelection);
+    pub fn get_time(env: Env) -> u64 {
+        env.ledger().timestamp()
+    }
+
+    /// Enters `user` into the raffle between its start and join end time.
+    /// Raffles with a ticket sale charge for one ticket.
+    pub fn join_raffle(env: Env, utility_id: u64, user: Address) {
+        user.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        raffle::require_open(&env, &u);
+        holding::require_held(&env, &u, None, &user);
+        eligibility::require_eligible(&env, utility_id, &user);
+        if let Some(sale) = tickets::load_sale(&env, utility_id) {
+            tickets::purchase(&env, &u, &sale, &user, 1);
         }
+        raffle::enter(&env, &u, &user);
+        points::award_entry(&env, &u, &user);
 
-        env.events()
-            .publish((symbol_short!("raffle"), symbol_short!("joined")), user);
+        events::raffle_joined(&env, utility_id, user);
     }
 
     pub fn end_raffle(env: Env, utility_id: u64, sender: Address) {
-        if env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .is_none()
-        {
-            panic_with_error!(&env, UtilityError::UtilityNotFound);
-        }
-        let utility = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap();
-        let mut u = utility.get(utility_id as u32).unwrap();
+        let mut u = Self::get_utility(env.clone(), utility_id);
 
-        if u.raffle.ended == true {
+        if u.raffle.ended {
             panic_with_error!(&env, UtilityError::RaffleAlreadyEnded)
         }
 
         if u.provider != sender.clone() {
             panic_with_error!(&env, UtilityError::NotAuthorized)
         }
+        sender.require_auth();
 
         if u.selection != Selection::Raffle {
             panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
@@ -230,139 +378,259 @@ impl UtilityContract {
             panic_with_error!(&env, UtilityError::RaffleNotEnded)
         }
 
-        u.raffle.ended = true;
+        if tickets::below_minimum(&env, &u) {
+            u.raffle.ended = true;
+            u.raffle.cancelled = true;
+            storage::save_utility(&env, utility_id, &u);
+
+            events::raffle_cancelled(&env, utility_id, raffle::entrant_count(&env, utility_id));
+            return;
+        }
+
+        let seed = env.prng().gen::<BytesN<32>>();
+        let winners = raffle::draw(&env, &u, &seed, u.reward.no_of_winners);
+        raffle::save_seed(&env, utility_id, &seed);
+        tickets::settle(&env, &u);
 
-        env.events().publish(
-            (symbol_short!("raffle"), symbol_short!("ended")),
-            symbol_short!("ended"),
+        u.raffle.ended = true;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::raffle_ended(&env, utility_id);
+        events::raffle_drawn(
+            &env,
+            utility_id,
+            seed,
+            raffle::total_weight(&env, utility_id),
+            winners,
         );
     }
+    /// Pays out `user`'s reward. The admin claims on behalf of users;
+    /// giveaway rewards can also be claimed by the user directly.
     pub fn claim_reward(env: Env, utility_id: u64, user: Address, sender: Address) {
-        // Check if the sender is the admin
-        let admin = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Address>(&ADMIN)
-            .unwrap();
-        if sender != admin {
-            panic_with_error!(&env, UtilityError::NotAuthorized)
-        }
+        let admin = read_admin(&env);
+        let mut utility = Self::get_utility(env.clone(), utility_id);
+        let giveaway = utility.utility_type == UtilityType::Giveaway;
 
-        let mut utilities = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap();
-        let mut utility = utilities.get(utility_id as u32).unwrap();
+        if sender == admin {
+            sender.require_auth();
+        } else {
+            if !(giveaway && sender == user) {
+                panic_with_error!(&env, UtilityError::NotAuthorized)
+            }
+            user.require_auth();
+        }
 
-        if utility.reward.total_amount < utility.reward.amount_per_win {
-            panic_with_error!(&env, UtilityError::AllRewardsClaimed)
+        if utility.paused {
+            panic_with_error!(&env, UtilityError::UtilityPaused)
+        }
+        if giveaway && utility.offer_expiry < env.ledger().timestamp() {
+            panic_with_error!(&env, UtilityError::UtilityExpired)
         }
 
-        utility.reward.total_amount -= utility.reward.amount_per_win;
+        let amount = take_reward(&env, &mut utility);
 
         if utility.selection == Selection::Raffle {
             if !utility.raffle.ended {
                 panic_with_error!(&env, UtilityError::RaffleNotEnded)
             }
-        }
-
-        // Check if already claimed
-        let claim_key = (symbol_short!("claimed"), utility_id, user.clone());
-        if env
-            .storage()
-            .persistent()
-            .get::<_, bool>(&claim_key)
-            .unwrap_or(false)
-        {
-            panic_with_error!(env, UtilityError::AlreadyClaimed)
-        }
-
-        // Mark as claimed
-        env.storage().persistent().set(&claim_key, &true);
-
-        let token_address_to_transfer: Address = utility.reward.token_addresses.get(0).unwrap();
-
-        match utility.reward.receipt {
-            Receipt::MintToken => {
-                let token_client = token::StellarAssetClient::new(&env, &token_address_to_transfer);
-                token_client.mint(&user, &(utility.reward.amount_per_win as i128));
-            }
-            Receipt::External => {
-                let token_client = token::TokenClient::new(&env, &token_address_to_transfer);
-                token_client.transfer_from(
-                    &env.current_contract_address(),
-                    &env.current_contract_address(),
-                    &user,
-                    &(utility.reward.amount_per_win as i128),
-                );
-            }
-
-            Receipt::None => {
-                panic_with_error!(&env, UtilityError::InvalidReceiptType)
+            if raffle::claims_closed(&env, &utility) {
+                panic_with_error!(&env, UtilityError::ClaimDeadlinePassed)
             }
-            Receipt::HTSToken => {
-                panic_with_error!(&env, UtilityError::InvalidReceiptType)
+            if !raffle::is_winner(&env, utility_id, &user) {
+                panic_with_error!(&env, UtilityError::NotWinner)
             }
         }
 
-        // Update the utility in storage
-        utilities.set(utility_id as u32, utility);
-        env.storage().persistent().set(&U_STORAGE, &utilities);
+        holding::require_held(&env, &utility, None, &user);
+        eligibility::require_eligible(&env, utility_id, &user);
+        require_unclaimed(&env, utility_id, &user);
+        if giveaway {
+            giveaway::record_claim(&env, &utility);
+        }
+        if utility.selection == Selection::Raffle {
+            raffle::record_claim(&env, utility_id, &user);
+        }
 
-        // Emit event
-        env.events().publish(
-            (symbol_short!("claim"), symbol_short!("reward")),
-            (utility_id, user, token_address_to_transfer),
-        );
+        settle_claim(&env, &utility, &user, amount);
     }
+    /// Flags `user` as eligible for the utility. The flag only gates
+    /// anything through a `MarkedEligible` eligibility rule.
     pub fn mark_eligible(env: Env, utility_id: u64, user: Address, sender: Address) {
         // Check if the sender is the admin
-        let admin = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Address>(&ADMIN)
-            .unwrap();
+        let admin = read_admin(&env);
         if sender != admin {
             panic_with_error!(&env, UtilityError::NotAuthorized)
         }
+        sender.require_auth();
 
-        let mut eligible = env
-            .storage()
+        env.storage()
             .persistent()
-            .get::<Symbol, Vec<Map<Address, u64>>>(&ELIGIBLE)
-            .unwrap_or(Vec::new(&env));
-        let mut utility_map = Map::new(&env);
-        utility_map.set(user, utility_id);
-        eligible.push_back(utility_map);
-        env.storage().persistent().set(&ELIGIBLE, &eligible);
+            .set(&(ELIGIBLE, utility_id, user.clone()), &true);
+
+        events::eligible_marked(&env, utility_id, user);
     }
-    pub fn get_utility(env: Env, utility_id: u64) -> Utility {
-        let utilities = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::UtilityNotFound));
 
-        utilities
-            .get(utility_id as u32)
+    pub fn is_eligible(env: Env, utility_id: u64, user: Address) -> bool {
+        env.storage()
+            .persistent()
+            .get::<(Symbol, u64, Address), bool>(&(ELIGIBLE, utility_id, user))
+            .unwrap_or(false)
+    }
+    pub fn get_utility(env: Env, utility_id: u64) -> Utility {
+        storage::load_utility(&env, utility_id)
             .unwrap_or_else(|| panic_with_error!(&env, UtilityError::UtilityNotFound))
     }
 
-    pub fn get_token_utility(env: Env, token_address: Address) -> Utility {
-        let token_utilities = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Map<Address, Utility>>>(&TOKEN_UTILITY)
-            .unwrap();
+    /// Returns up to `limit` utilities in creation order starting at id
+    /// `cursor`. Pass the returned `next_cursor` to fetch the next page.
+    pub fn list_utilities(env: Env, cursor: u64, limit: u32) -> UtilityPage {
+        let limit = limit.min(config::max_page_size(&env));
+        let count = storage::utility_count(&env);
+        let mut utilities = Vec::new(&env);
+
+        let mut id = cursor;
+        while id < count && utilities.len() < limit {
+            if let Some(u) = storage::load_utility(&env, id) {
+                utilities.push_back(u);
+            }
+            id += 1;
+        }
+
+        UtilityPage {
+            utilities,
+            next_cursor: if id < count { Some(id) } else { None },
+        }
+    }
+
+    /// Lists utilities created by `provider`. The cursor is a position in
+    /// the provider index, not a utility id.
+    pub fn list_utilities_by_provider(
+        env: Env,
+        provider: Address,
+        cursor: u64,
+        limit: u32,
+    ) -> UtilityPage {
+        list_index(
+            &env,
+            storage::BY_PROVIDER,
+            storage::BY_PROVIDER_COUNT,
+            &provider,
+            cursor,
+            limit,
+        )
+    }
+
+    /// Lists utilities naming `partner`. The cursor is a position in the
+    /// partner index, not a utility id.
+    pub fn list_utilities_by_partner(
+        env: Env,
+        partner: Address,
+        cursor: u64,
+        limit: u32,
+    ) -> UtilityPage {
+        list_index(
+            &env,
+            storage::BY_PARTNER,
+            storage::BY_PARTNER_COUNT,
+            &partner,
+            cursor,
+            limit,
+        )
+    }
+
+    /// Lists utilities that are unpaused, not yet past their offer expiry
+    /// at `now` and, for raffles, not ended. At most `MAX_SCAN` ids are
+    /// inspected per call, so a page may come back short with a
+    /// `next_cursor` still set.
+    pub fn list_active_utilities(env: Env, now: u64, cursor: u64, limit: u32) -> UtilityPage {
+        let limit = limit.min(config::max_page_size(&env));
+        let count = storage::utility_count(&env);
+        let scan_end = count.min(cursor.saturating_add(MAX_SCAN));
+        let mut utilities = Vec::new(&env);
+
+        let mut id = cursor;
+        while id < scan_end && utilities.len() < limit {
+            if let Some(u) = storage::load_utility(&env, id) {
+                if is_active(&u, now) {
+                    utilities.push_back(u);
+                }
+            }
+            id += 1;
+        }
 
-        for m in token_utilities {
-            if m.contains_key(token_address.clone()) {
-                return m.get(token_address).unwrap();
+        UtilityPage {
+            utilities,
+            next_cursor: if id < count { Some(id) } else { None },
+        }
+    }
+
+    pub fn get_token_utility(env: Env, token_address: Address, utility_id: u64) -> TokenUtility {
+        storage::load_token_utility(&env, &token_address, utility_id)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::UtilityNotClaimed))
+    }
+
+    /// Returns every utility bound to an NFT in `collections` that `user`
+    /// currently holds. At most `max_batch_size` collections and
+    /// `max_page_size` utilities per collection are inspected.
+    pub fn get_user_benefits(
+        env: Env,
+        user: Address,
+        collections: Vec<Address>,
+    ) -> Vec<UserBenefit> {
+        let now = env.ledger().timestamp();
+        let mut benefits = Vec::new(&env);
+
+        let page_size = config::max_page_size(&env) as u64;
+        for token in collections
+            .iter()
+            .take(config::max_batch_size(&env) as usize)
+        {
+            if !Self::check_nft_ownership(env.clone(), token.clone(), user.clone()) {
+                continue;
+            }
+
+            let len = storage::index_len(&env, storage::BY_TOKEN_COUNT, &token).min(page_size);
+            for position in 0..len {
+                let Some(utility_id) =
+                    storage::index_get(&env, storage::BY_TOKEN, &token, position)
+                else {
+                    continue;
+                };
+                let (Some(u), Some(token_utility)) = (
+                    storage::load_utility(&env, utility_id),
+                    storage::load_token_utility(&env, &token, utility_id),
+                ) else {
+                    continue;
+                };
+
+                let raffle_status = if u.selection != Selection::Raffle {
+                    RaffleStatus::NotRaffle
+                } else if u.raffle.cancelled {
+                    RaffleStatus::Cancelled
+                } else if u.raffle.ended {
+                    RaffleStatus::Ended
+                } else if now < u.raffle.start_time {
+                    RaffleStatus::Upcoming
+                } else {
+                    RaffleStatus::Open
+                };
+
+                benefits.push_back(UserBenefit {
+                    token: token.clone(),
+                    utility_id,
+                    utility_uri: u.utility_uri.clone(),
+                    usage_type: u.usage_type,
+                    usage_left: token_utility.usage,
+                    expiry: token_utility.expiry,
+                    redeemable: Self::check_utility(env.clone(), token.clone(), utility_id),
+                    reward_claimed: has_claimed_reward(&env, utility_id, &user),
+                    raffle_status,
+                    eligible: Self::evaluate_eligibility(env.clone(), utility_id, user.clone()),
+                });
             }
         }
 
-        panic_with_error!(&env, UtilityError::UtilityNotFound);
+        benefits
     }
 
     pub fn claim_utitlity_on_nft(
@@ -372,117 +640,303 @@ impl UtilityContract {
         user: Address,
         sender: Address,
     ) {
-        let admin = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Address>(&ADMIN)
-            .unwrap();
+        let admin = read_admin(&env);
         if sender != admin {
-            panic!("Not authorized");
+            panic_with_error!(&env, UtilityError::NotAuthorized)
         }
+        sender.require_auth();
 
-        let utility = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap();
-        let u = utility.get(utility_id as u32).unwrap();
+        let u = Self::get_utility(env.clone(), utility_id);
 
         if u.provider != sender.clone() {
             panic_with_error!(&env, UtilityError::NotAuthorized)
         }
 
-        let mut token_utility = Self::get_token_utility(env.clone(), token_address.clone());
+        if u.paused {
+            panic_with_error!(&env, UtilityError::UtilityPaused)
+        }
+        eligibility::require_eligible(&env, utility_id, &user);
+
+        // A grant is made once; claiming again would reset its usage and
+        // expiry.
+        if storage::load_token_utility(&env, &token_address, utility_id).is_some() {
+            panic_with_error!(&env, UtilityError::AlreadyClaimed)
+        }
+        let mut token_utility = TokenUtility {
+            token: token_address.clone(),
+            utility_id,
+            owner: user.clone(),
+            usage: 0,
+            expiry: 0,
+            claimed_at: 0,
+        };
 
         if u.selection == Selection::Raffle {
             // check merkle proof
         }
 
-        if token_utility.usage_type == UsageType::Limited {
-            token_utility.usage = u.usage;
+        token_utility.owner = user.clone();
+        grant_utility(&env, &u, &mut token_utility);
+        storage::save_token_utility(&env, &token_utility);
+
+        events::utility_claimed(&env, utility_id, token_address, user, token_utility.expiry);
+    }
+
+    /// Redeems one use of `utility_id` on `token_address`. `user` must hold
+    /// the NFT or be its active delegate.
+    pub fn redeem_utility_on_nft(env: Env, token_address: Address, utility_id: u64, user: Address) {
+        user.require_auth();
+        let delegation = delegation::authorize_redemption(&env, &token_address, utility_id, &user);
+
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        let holder = delegation.as_ref().map_or(&user, |d| &d.owner);
+        if let Some(mut token_utility) =
+            storage::load_token_utility(&env, &token_address, utility_id)
+        {
+            if !transfer::apply_policy(&env, &u, &mut token_utility, holder) {
+                panic_with_error!(&env, UtilityError::SoulboundUtility)
+            }
         }
+        holding::require_held(&env, &u, Some(&token_address), holder);
+        eligibility::require_eligible(&env, utility_id, holder);
 
-        if u.expiry_type == ExpiryType::TimeBased {
-            token_utility.expiry = u.expiry + env.ledger().timestamp();
-        } else if u.expiry_type == ExpiryType::DateBased {
-            token_utility.expiry = u.expiry;
+        let status = Self::utility_status(env.clone(), token_address.clone(), utility_id);
+        match status.reason {
+            StatusReason::Redeemable => {}
+            StatusReason::NotClaimed => panic_with_error!(&env, UtilityError::UtilityNotClaimed),
+            StatusReason::Paused => panic_with_error!(&env, UtilityError::UtilityPaused),
+            StatusReason::Expired => panic_with_error!(&env, UtilityError::UtilityExpired),
+            StatusReason::UsageExhausted => panic_with_error!(&env, UtilityError::UsageExceeded),
         }
 
-        env.events().publish(
-            (symbol_short!("utility"), symbol_short!("claimed")),
-            (token_address, user, utility_id),
-        );
+        let mut token_utility =
+            Self::get_token_utility(env.clone(), token_address.clone(), utility_id);
+
+        if u.usage_type == UsageType::Limited {
+            token_utility.usage -= 1;
+            storage::save_token_utility(&env, &token_utility);
+        }
+        coupons::assign(&env, &u, &user);
+        points::award_redemption(&env, &u, holder);
+        receipts::issue(&env, &u, holder);
+        settlement::settle(&env, &u);
+        if let Some(d) = delegation {
+            delegation::record_use(&env, d);
+        }
+
+        events::utility_redeemed(&env, utility_id, token_address, user, token_utility.usage);
     }
 
-    pub fn redeem_utility_on_nft(env: Env, token_address: Address, utility_id: u64, user: Address) {
-        let owner = Self::check_nft_ownership(env.clone(), token_address.clone(), user.clone());
+    pub fn check_utility(env: Env, token_address: Address, utility_id: u64) -> bool {
+        Self::utility_status(env, token_address, utility_id).redeemable
+    }
 
-        if !owner {
-            panic_with_error!(&env, UtilityError::NotAuthorized)
+    /// Reports what the holder of `token_address` can currently do with
+    /// `utility_id`. `reason` explains why the utility is not redeemable;
+    /// it is `StatusReason::Redeemable` otherwise.
+    pub fn utility_status(
+        env: Env,
+        token_address: Address,
+        utility_id: u64,
+    ) -> UtilityStatusReport {
+        let u = Self::get_utility(env.clone(), utility_id);
+        let token_utility = storage::load_token_utility(&env, &token_address, utility_id);
+
+        let remaining_usages = match (u.usage_type, &token_utility) {
+            (UsageType::Unlimited, _) => None,
+            (UsageType::Limited, Some(t)) => Some(t.usage),
+            (UsageType::Limited, None) => Some(0),
+        };
+        let expires_at = match (u.expiry_type, &token_utility) {
+            (ExpiryType::None, _) => None,
+            (_, Some(t)) => Some(t.expiry),
+            (_, None) => None,
+        };
+
+        let reason = if token_utility.is_none() {
+            StatusReason::NotClaimed
+        } else if u.paused {
+            StatusReason::Paused
+        } else if expires_at.is_some_and(|expiry| expiry < env.ledger().timestamp()) {
+            StatusReason::Expired
+        } else if remaining_usages == Some(0) {
+            StatusReason::UsageExhausted
+        } else {
+            StatusReason::Redeemable
+        };
+
+        UtilityStatusReport {
+            utility_id,
+            token: token_address,
+            remaining_usages,
+            expires_at,
+            redeemable: reason == StatusReason::Redeemable,
+            reason,
         }
+    }
+}
 
-        let utility = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-            .unwrap();
-        let mut u = utility.get(utility_id as u32).unwrap();
+/// Sets the usage and expiry `u` grants to a freshly claimed NFT.
+fn grant_utility(env: &Env, u: &Utility, token_utility: &mut TokenUtility) {
+    let now = env.ledger().timestamp();
+    token_utility.claimed_at = now;
 
-        let token_utility = Self::get_token_utility(env.clone(), token_address.clone());
+    if u.usage_type == UsageType::Limited {
+        token_utility.usage = u.usage;
+    }
 
-        if u.expiry_type == ExpiryType::None && token_utility.expiry < env.ledger().timestamp() {
-            panic_with_error!(&env, UtilityError::UtilityExpired)
+    if u.expiry_type == ExpiryType::TimeBased {
+        token_utility.expiry = u.expiry + now;
+    } else if u.expiry_type == ExpiryType::DateBased {
+        token_utility.expiry = u.expiry;
+    }
+}
+
+/// The token `u` pays its reward in, failing for receipts that do not pay
+/// out a token.
+fn reward_token(env: &Env, u: &Utility) -> Address {
+    match u.reward.receipt {
+        Receipt::MintToken | Receipt::External | Receipt::Points => {
+            param(env, &u.reward.token_addresses, 0)
         }
+        Receipt::None | Receipt::HTSToken => {
+            panic_with_error!(env, UtilityError::InvalidReceiptType)
+        }
+    }
+}
 
-        if token_utility.usage_type == UsageType::Limited {
-            // token_utility.usage = u.usage;
-            if u.usage == 0 {
-                panic_with_error!(&env, UtilityError::UsageExceeded)
-            } else {
-                u.usage -= 1;
-            }
+/// Decimals of the token a `receipt` reward pays out in, or 0 when it pays
+/// no token.
+fn reward_decimals(env: &Env, receipt: &Receipt, token_addresses: &Vec<Address>) -> u32 {
+    match (receipt, token_addresses.get(0)) {
+        (Receipt::MintToken | Receipt::External | Receipt::Points, Some(token_address)) => {
+            token::TokenClient::new(env, &token_address).decimals()
         }
+        _ => 0,
+    }
+}
+
+/// Rejects negative amounts and a pool too small to pay every winner.
+fn validate_reward(env: &Env, reward: &Reward) {
+    if reward.total_amount < 0 || reward.amount_per_win < 0 {
+        panic_with_error!(env, RewardError::InvalidRewardAmount)
+    }
+    let needed = reward
+        .amount_per_win
+        .checked_mul(reward.no_of_winners as i128)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    if needed > reward.total_amount {
+        panic_with_error!(env, RewardError::InvalidRewardAmount)
+    }
+}
 
-        env.events().publish(
-            (symbol_short!("utility"), symbol_short!("redeemed")),
-            (token_address, user, utility_id),
+/// Pays `amount` of `u`'s reward to `to` and returns the reward token.
+/// Rewards paid out of escrow have the platform fee skimmed off.
+fn pay_reward(env: &Env, u: &Utility, to: &Address, amount: i128) -> Address {
+    let token_address = reward_token(env, u);
+    if u.reward.receipt == Receipt::MintToken {
+        token::StellarAssetClient::new(env, &token_address).mint(to, &amount);
+    } else if u.reward.receipt == Receipt::Points {
+        points::mint(env, &token_address, to, amount);
+    } else {
+        let fee = fees::platform_fee(env, amount);
+        escrow::debit(env, u.id, amount);
+        token::TokenClient::new(env, &token_address).transfer(
+            &env.current_contract_address(),
+            to,
+            &(amount - fee),
         );
+        fees::accrue(env, u.id, &token_address, fee);
     }
+    token_address
+}
 
-    pub fn check_utility(env: Env, token_address: Address, utility_id: u64) -> bool {
-        let mut response = true;
-        if let Some(utility) = env
-            .storage()
-            .persistent()
-            .get::<Symbol, Vec<Utility>>(&U_STORAGE)
-        {
-            let u = utility.get(utility_id as u32);
+fn has_claimed_reward(env: &Env, utility_id: u64, user: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .get::<_, bool>(&(CLAIMED, utility_id, user.clone()))
+        .unwrap_or(false)
+}
 
-            match u {
-                Some(u) => {
-                    let token_utility = Self::get_token_utility(env.clone(), token_address.clone());
+fn require_unclaimed(env: &Env, utility_id: u64, user: &Address) {
+    if has_claimed_reward(env, utility_id, user) {
+        panic_with_error!(env, UtilityError::AlreadyClaimed)
+    }
+}
 
-                    if u.expiry_type == ExpiryType::None {
-                        if token_utility.expiry < env.ledger().timestamp() {
-                            response = false;
-                        }
-                    }
+/// Takes one reward out of `u`'s pool and returns its amount in reward
+/// tokens.
+fn take_reward(env: &Env, u: &mut Utility) -> i128 {
+    let amount = pricing::reward_amount(env, u);
+    if u.reward.total_amount < amount {
+        panic_with_error!(env, UtilityError::AllRewardsClaimed)
+    }
+    u.reward.total_amount -= amount;
+    amount
+}
 
-                    if u.usage_type != UsageType::Limited {
-                        if token_utility.usage == 0 {
-                            response = false;
-                        }
-                    }
+/// Marks `user`'s reward of `amount` claimed and pays it out, or starts it
+/// vesting. The caller has already taken it out of `u`'s pool.
+fn settle_claim(env: &Env, u: &Utility, user: &Address, amount: i128) {
+    env.storage()
+        .persistent()
+        .set(&(CLAIMED, u.id, user.clone()), &true);
 
-                    response
-                }
-                None => {
-                    panic_with_error!(&env, UtilityError::UtilityNotFound)
-                }
-            }
-        } else {
-            // panic_with_error!(&env, UtilityError::UtilityNotFound)
-            return false;
+    let token_address = if u.reward.vesting == Vesting::None {
+        pay_reward(env, u, user, amount)
+    } else {
+        vesting::grant(env, u, user, amount)
+    };
+
+    storage::save_utility(env, u.id, u);
+
+    events::reward_claimed(env, u.id, user.clone(), token_address, amount);
+}
+
+fn read_admin(env: &Env) -> Address {
+    config::read(env).admin
+}
+
+/// Reads a required positional argument, failing with `MissingParameter`
+/// when the caller passed too short a vector.
+fn param<T>(env: &Env, values: &Vec<T>, index: u32) -> T
+where
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    values
+        .get(index)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::MissingParameter))
+}
+
+fn is_active(u: &Utility, now: u64) -> bool {
+    !u.paused && u.offer_expiry >= now && !(u.selection == Selection::Raffle && u.raffle.ended)
+}
+
+fn list_index(
+    env: &Env,
+    key: Symbol,
+    count_key: Symbol,
+    owner: &Address,
+    cursor: u64,
+    limit: u32,
+) -> UtilityPage {
+    let limit = limit.min(config::max_page_size(env));
+    let len = storage::index_len(env, count_key, owner);
+    let mut utilities = Vec::new(env);
+
+    let mut position = cursor;
+    while position < len && utilities.len() < limit {
+        if let Some(u) = storage::index_get(env, key.clone(), owner, position)
+            .and_then(|id| storage::load_utility(env, id))
+        {
+            utilities.push_back(u);
         }
+        position += 1;
+    }
+
+    UtilityPage {
+        utilities,
+        next_cursor: if position < len { Some(position) } else { None },
     }
 }
@@ -0,0 +1,102 @@
+//! Loyalty points rewards.
+//!
+//! A `Receipt::Points` utility pays its reward by minting from the points
+//! token in `token_addresses[0]`, for which this contract must be an
+//! appointed minter. The provider may additionally award points for every
+//! redemption and every raffle entry with `set_points_award`.
+
+use soroban_sdk::{
+    contractclient, contractimpl, panic_with_error, symbol_short, Address, Env, Symbol,
+};
+
+use crate::structs::{PointsAward, Receipt, Utility, UtilityError};
+use crate::{config, events, reward_token, UtilityContract, UtilityContractClient};
+
+pub const POINTS: Symbol = symbol_short!("POINTS");
+
+/// The part of the points token interface this contract calls.
+#[allow(dead_code)]
+#[contractclient(name = "PointsClient")]
+pub trait PointsInterface {
+    fn mint(env: Env, minter: Address, to: Address, amount: i128);
+}
+
+pub fn load_award(env: &Env, utility_id: u64) -> Option<PointsAward> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), PointsAward>(&(POINTS, utility_id))
+}
+
+/// Mints `amount` points of `token_address` to `to`.
+pub fn mint(env: &Env, token_address: &Address, to: &Address, amount: i128) {
+    PointsClient::new(env, token_address).mint(&env.current_contract_address(), to, &amount);
+}
+
+fn award(env: &Env, u: &Utility, user: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    let token_address = reward_token(env, u);
+    mint(env, &token_address, user, amount);
+
+    events::points_awarded(env, u.id, user.clone(), token_address, amount);
+}
+
+pub fn award_redemption(env: &Env, u: &Utility, user: &Address) {
+    if let Some(a) = load_award(env, u.id) {
+        award(env, u, user, a.per_redemption);
+    }
+}
+
+pub fn award_entry(env: &Env, u: &Utility, user: &Address) {
+    if let Some(a) = load_award(env, u.id) {
+        award(env, u, user, a.per_entry);
+    }
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Awards `per_redemption` points for each redemption and `per_entry`
+    /// points for each raffle entry. Only `Receipt::Points` utilities can
+    /// award points; zero for both removes the award.
+    pub fn set_points_award(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        per_redemption: i128,
+        per_entry: i128,
+    ) {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.reward.receipt != Receipt::Points {
+            panic_with_error!(&env, UtilityError::InvalidReceiptType)
+        }
+        if per_redemption < 0 || per_entry < 0 {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        let key = (POINTS, utility_id);
+        if per_redemption == 0 && per_entry == 0 {
+            env.storage().persistent().remove(&key);
+        } else {
+            env.storage().persistent().set(
+                &key,
+                &PointsAward {
+                    per_redemption,
+                    per_entry,
+                },
+            );
+            config::extend_persistent(&env, &key);
+        }
+
+        events::points_award_set(&env, utility_id, per_redemption, per_entry);
+    }
+
+    pub fn get_points_award(env: Env, utility_id: u64) -> Option<PointsAward> {
+        load_award(&env, utility_id)
+    }
+}
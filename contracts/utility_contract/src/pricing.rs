@@ -0,0 +1,187 @@
+//! Oracle-priced rewards.
+//!
+//! A provider budgeting in a reference currency can price a utility's
+//! reward through a SEP-40 price oracle. Each win is then worth
+//! `reference_amount` in the oracle's quote currency, at the oracle's
+//! precision, converted into reward tokens at claim time using `lastprice`
+//! for the reward token. `amount_per_win` keeps its meaning in reward
+//! tokens and applies again once pricing is removed.
+//! Prices older than `max_age` seconds, or further than
+//! `max_deviation_bps` from the last accepted price, are rejected. No claim
+//! draws more than `max_payout` tokens, nor more than is left in the pool
+//! and, for escrowed rewards, in the utility's escrow.
+
+use soroban_sdk::{
+    contractclient, contractimpl, contracttype, panic_with_error, symbol_short, Address, Env,
+    Symbol,
+};
+
+use crate::fees::BPS_DENOMINATOR;
+use crate::structs::{PricedReward, Receipt, RewardError, Utility, UtilityError};
+use crate::{config, escrow, events, reward_token, UtilityContract, UtilityContractClient};
+
+pub const PRICING: Symbol = symbol_short!("PRICING");
+
+/// Asset identifier as defined by SEP-40.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Asset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+/// A SEP-40 price record.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+/// The part of the SEP-40 oracle interface this contract calls.
+#[allow(dead_code)]
+#[contractclient(name = "PriceOracleClient")]
+pub trait PriceOracleInterface {
+    fn lastprice(env: Env, asset: Asset) -> Option<PriceData>;
+}
+
+pub fn load(env: &Env, utility_id: u64) -> Option<PricedReward> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), PricedReward>(&(PRICING, utility_id))
+}
+
+fn save(env: &Env, utility_id: u64, pricing: &PricedReward) {
+    let key = (PRICING, utility_id);
+    env.storage().persistent().set(&key, pricing);
+    config::extend_persistent(env, &key);
+}
+
+/// The oracle's current price of `u`'s reward token, rejecting missing,
+/// non-positive and stale prices.
+fn fresh_price(env: &Env, u: &Utility, oracle: &Address, max_age: u64) -> i128 {
+    let asset = Asset::Stellar(reward_token(env, u));
+    let Some(data) = PriceOracleClient::new(env, oracle).lastprice(&asset) else {
+        panic_with_error!(env, RewardError::StalePrice)
+    };
+    if data.price <= 0 {
+        panic_with_error!(env, RewardError::InvalidPrice)
+    }
+    if env.ledger().timestamp().saturating_sub(data.timestamp) > max_age {
+        panic_with_error!(env, RewardError::StalePrice)
+    }
+    data.price
+}
+
+/// The reward one claim on `u` pays, in reward tokens.
+pub fn reward_amount(env: &Env, u: &Utility) -> i128 {
+    let Some(mut p) = load(env, u.id) else {
+        return u.reward.amount_per_win;
+    };
+    let price = fresh_price(env, u, &p.oracle, p.max_age);
+
+    let overflow = || panic_with_error!(env, UtilityError::ArithmeticOverflow);
+    let deviation = (price - p.last_price).abs();
+    let allowed = p
+        .last_price
+        .checked_mul(p.max_deviation_bps as i128)
+        .unwrap_or_else(overflow);
+    if deviation
+        .checked_mul(BPS_DENOMINATOR)
+        .unwrap_or_else(overflow)
+        > allowed
+    {
+        panic_with_error!(env, RewardError::PriceDeviation)
+    }
+
+    let unit = 10_i128
+        .checked_pow(u.reward.decimals)
+        .unwrap_or_else(overflow);
+    let amount = p
+        .reference_amount
+        .checked_mul(unit)
+        .unwrap_or_else(overflow)
+        / price;
+
+    p.last_price = price;
+    save(env, u.id, &p);
+
+    let mut cap = p.max_payout.min(u.reward.total_amount);
+    if u.reward.receipt == Receipt::External {
+        cap = cap.min(escrow::balance(env, u.id));
+    }
+    if cap <= 0 {
+        panic_with_error!(env, UtilityError::AllRewardsClaimed)
+    }
+    let amount = amount.min(cap);
+    events::reward_priced(env, u.id, price, amount);
+    amount
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Prices the utility's reward through `oracle`, taking its current
+    /// price as the reference for the deviation limit. From now on each
+    /// win pays `reference_amount` of the oracle's quote currency in
+    /// reward tokens.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_reward_pricing(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        oracle: Address,
+        reference_amount: i128,
+        max_age: u64,
+        max_deviation_bps: u32,
+        max_payout: i128,
+    ) -> PricedReward {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if !matches!(
+            u.reward.receipt,
+            Receipt::MintToken | Receipt::External | Receipt::Points
+        ) {
+            panic_with_error!(&env, UtilityError::InvalidReceiptType)
+        }
+        if reference_amount <= 0 || max_payout <= 0 || max_deviation_bps as i128 > BPS_DENOMINATOR {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        let pricing = PricedReward {
+            last_price: fresh_price(&env, &u, &oracle, max_age),
+            oracle,
+            reference_amount,
+            max_age,
+            max_deviation_bps,
+            max_payout,
+        };
+        save(&env, utility_id, &pricing);
+
+        events::reward_pricing_set(
+            &env,
+            utility_id,
+            Some(pricing.oracle.clone()),
+            pricing.reference_amount,
+            pricing.max_payout,
+        );
+        pricing
+    }
+
+    /// Goes back to paying `amount_per_win` in reward tokens.
+    pub fn remove_reward_pricing(env: Env, utility_id: u64, sender: Address) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        env.storage().persistent().remove(&(PRICING, utility_id));
+
+        events::reward_pricing_set(&env, utility_id, None, 0, 0);
+    }
+
+    pub fn get_reward_pricing(env: Env, utility_id: u64) -> Option<PricedReward> {
+        load(&env, utility_id)
+    }
+}
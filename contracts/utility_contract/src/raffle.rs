@@ -0,0 +1,571 @@
+//! Raffle entrants and the weighted draw.
+//!
+//! Entrants are appended to a single list under `(ENTRANTS, utility_id)`
+//! when they join, together with the weight their `EntryWeight` gave them
+//! at that moment, and winners are tracked in a single map under
+//! `(WON, utility_id)`. The draw therefore reads a fixed number of ledger
+//! entries however many entrants there are; `MAX_ENTRANTS` keeps the list
+//! within the size of one entry. `end_raffle` picks up to `no_of_winners`
+//! distinct winners with probability proportional to weight.
+//!
+//! The draw is reproducible from the published seed: for round `i` the
+//! first eight bytes of `sha256(seed || i as u32 big-endian)`, read as a
+//! big-endian `u64` modulo the weight still in play, select the entrant
+//! whose cumulative weight range contains it, walking entrants in join
+//! order and skipping everyone drawn in an earlier round. Rerolls of
+//! unclaimed prizes continue the round numbering from the same seed.
+
+use soroban_sdk::{
+    contractimpl, contracttype, panic_with_error, symbol_short, token, Address, Bytes, BytesN, Env,
+    Map, Symbol, Vec,
+};
+
+use crate::structs::{EntryWeight, RaffleEntry, Receipt, Selection, Utility, UtilityError};
+use crate::{
+    config, escrow, events, reward_token, storage, UtilityContract, UtilityContractClient,
+};
+
+pub const ENTRANTS: Symbol = symbol_short!("ENTRANTS");
+pub const ENTERED: Symbol = symbol_short!("ENTERED");
+pub const TOTAL_WEIGHT: Symbol = symbol_short!("TOT_WGT");
+pub const TICKETS: Symbol = symbol_short!("TICKETS");
+pub const WINNERS: Symbol = symbol_short!("WINNERS");
+pub const WON: Symbol = symbol_short!("WON");
+pub const SEED: Symbol = symbol_short!("SEED");
+pub const ROUNDS: Symbol = symbol_short!("ROUNDS");
+
+/// Largest weight a single entry carries, so the weights of every entrant
+/// add up without overflowing.
+pub const MAX_ENTRY_WEIGHT: u64 = u64::MAX >> 16;
+
+/// Most entrants a raffle accepts, and the cap when `max_entrants` is 0.
+pub const MAX_ENTRANTS: u32 = 250;
+
+/// Where a drawn entrant stands with their prize.
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinStatus {
+    Won,
+    Claimed,
+    Lapsed,
+}
+
+fn entrants(env: &Env, utility_id: u64) -> Vec<RaffleEntry> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Vec<RaffleEntry>>(&(ENTRANTS, utility_id))
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+fn save_entrants(env: &Env, utility_id: u64, entries: &Vec<RaffleEntry>) {
+    let key = (ENTRANTS, utility_id);
+    env.storage().persistent().set(&key, entries);
+    config::extend_persistent(env, &key);
+}
+
+fn win_statuses(env: &Env, utility_id: u64) -> Map<Address, WinStatus> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Map<Address, WinStatus>>(&(WON, utility_id))
+        .unwrap_or_else(|| Map::new(env))
+}
+
+fn save_win_statuses(env: &Env, utility_id: u64, statuses: &Map<Address, WinStatus>) {
+    let key = (WON, utility_id);
+    env.storage().persistent().set(&key, statuses);
+    config::extend_persistent(env, &key);
+}
+
+pub fn entrant_count(env: &Env, utility_id: u64) -> u32 {
+    entrants(env, utility_id).len()
+}
+
+pub fn total_weight(env: &Env, utility_id: u64) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), u64>(&(TOTAL_WEIGHT, utility_id))
+        .unwrap_or(0)
+}
+
+pub fn has_entered(env: &Env, utility_id: u64, user: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .has(&(ENTERED, utility_id, user.clone()))
+}
+
+pub fn tickets(env: &Env, utility_id: u64, user: &Address) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, Address), u64>(&(TICKETS, utility_id, user.clone()))
+        .unwrap_or(0)
+}
+
+pub fn is_winner(env: &Env, utility_id: u64, user: &Address) -> bool {
+    matches!(
+        win_statuses(env, utility_id).get(user.clone()),
+        Some(WinStatus::Won | WinStatus::Claimed)
+    )
+}
+
+/// Records that `user` has claimed their prize.
+pub fn record_claim(env: &Env, utility_id: u64, user: &Address) {
+    let mut statuses = win_statuses(env, utility_id);
+    statuses.set(user.clone(), WinStatus::Claimed);
+    save_win_statuses(env, utility_id, &statuses);
+}
+
+fn balance(env: &Env, token_address: &Address, user: &Address) -> u64 {
+    let balance = token::TokenClient::new(env, token_address).balance(user);
+    u64::try_from(balance.max(0)).unwrap_or(u64::MAX)
+}
+
+/// Weight `user` would enter `u` with right now, up to `MAX_ENTRY_WEIGHT`.
+pub fn weight_of(env: &Env, u: &Utility, user: &Address) -> u64 {
+    let weight = match &u.raffle.weight {
+        EntryWeight::Equal => 1,
+        EntryWeight::NftCount(collection) => balance(env, collection, user),
+        EntryWeight::TokenBalance(token_address, unit) => {
+            balance(env, token_address, user) / u64::try_from(*unit).unwrap_or(u64::MAX)
+        }
+        EntryWeight::Tickets => tickets(env, u.id, user),
+    };
+    weight.min(MAX_ENTRY_WEIGHT)
+}
+
+/// Fails unless `u` is a raffle currently accepting entries.
+pub fn require_open(env: &Env, u: &Utility) {
+    if u.paused {
+        panic_with_error!(env, UtilityError::UtilityPaused)
+    }
+    if u.selection != Selection::Raffle || u.raffle.ended {
+        panic_with_error!(env, UtilityError::InvalidRaffleSelection)
+    }
+
+    let now = env.ledger().timestamp();
+    if now < u.raffle.start_time {
+        panic_with_error!(env, UtilityError::RaffleNotStarted)
+    }
+    if now > u.raffle.join_end_time || now > u.offer_expiry {
+        panic_with_error!(env, UtilityError::RaffleExpired)
+    }
+}
+
+/// Records `user` as an entrant with their current weight.
+pub fn enter(env: &Env, u: &Utility, user: &Address) -> u64 {
+    if has_entered(env, u.id, user) {
+        panic_with_error!(env, UtilityError::AlreadyJoined)
+    }
+    let mut entries = entrants(env, u.id);
+    let position = entries.len();
+    let cap = match u.raffle.max_entrants {
+        0 => MAX_ENTRANTS,
+        max => max.min(MAX_ENTRANTS),
+    };
+    if position >= cap {
+        panic_with_error!(env, UtilityError::RaffleFull)
+    }
+    let weight = weight_of(env, u, user);
+    if weight == 0 {
+        panic_with_error!(env, UtilityError::NoEntryWeight)
+    }
+
+    entries.push_back(RaffleEntry {
+        user: user.clone(),
+        weight,
+    });
+    save_entrants(env, u.id, &entries);
+
+    let entered_key = (ENTERED, u.id, user.clone());
+    env.storage().persistent().set(&entered_key, &position);
+    config::extend_persistent(env, &entered_key);
+
+    let total = total_weight(env, u.id).saturating_add(weight);
+    env.storage()
+        .persistent()
+        .set(&(TOTAL_WEIGHT, u.id), &total);
+    weight
+}
+
+pub fn save_seed(env: &Env, utility_id: u64, seed: &BytesN<32>) {
+    let key = (SEED, utility_id);
+    env.storage().persistent().set(&key, seed);
+    config::extend_persistent(env, &key);
+}
+
+/// Adds `extra` weight to an existing entry of `user`.
+pub fn add_weight(env: &Env, utility_id: u64, user: &Address, extra: u64) {
+    let Some(position) = env
+        .storage()
+        .persistent()
+        .get::<(Symbol, u64, Address), u32>(&(ENTERED, utility_id, user.clone()))
+    else {
+        return;
+    };
+    let mut entries = entrants(env, utility_id);
+    if let Some(mut entry) = entries.get(position) {
+        let weight = entry.weight.saturating_add(extra).min(MAX_ENTRY_WEIGHT);
+        let added = weight - entry.weight;
+        entry.weight = weight;
+        entries.set(position, entry);
+        save_entrants(env, utility_id, &entries);
+
+        let total = total_weight(env, utility_id).saturating_add(added);
+        env.storage()
+            .persistent()
+            .set(&(TOTAL_WEIGHT, utility_id), &total);
+    }
+}
+
+/// Adds `count` tickets to `user`'s balance for `utility_id`.
+pub fn add_tickets(env: &Env, utility_id: u64, user: &Address, count: u64) -> u64 {
+    let key = (TICKETS, utility_id, user.clone());
+    let total = tickets(env, utility_id, user).saturating_add(count);
+    env.storage().persistent().set(&key, &total);
+    config::extend_persistent(env, &key);
+    total
+}
+
+fn draw_point(env: &Env, seed: &BytesN<32>, round: u32, remaining: u64) -> u64 {
+    let mut input = Bytes::from_array(env, &seed.to_array());
+    input.extend_from_array(&round.to_be_bytes());
+    let digest = env.crypto().sha256(&input).to_array();
+
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(head) % remaining
+}
+
+pub fn rounds_drawn(env: &Env, utility_id: u64) -> u32 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), u32>(&(ROUNDS, utility_id))
+        .unwrap_or(0)
+}
+
+/// Everyone drawn so far, including winners whose prize lapsed.
+pub fn drawn(env: &Env, utility_id: u64) -> Vec<Address> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Vec<Address>>(&(WINNERS, utility_id))
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+/// Whether the claim deadline of `u` has passed.
+pub fn claims_closed(env: &Env, u: &Utility) -> bool {
+    u.raffle.claim_deadline > 0 && env.ledger().timestamp() > u.raffle.claim_deadline
+}
+
+/// Withdraws the wins of winners who have not claimed their prize and
+/// returns them in draw order. They stay excluded from later draws.
+fn lapse_unclaimed(env: &Env, utility_id: u64) -> Vec<Address> {
+    let mut statuses = win_statuses(env, utility_id);
+    let mut lapsed = Vec::new(env);
+    for user in drawn(env, utility_id).iter() {
+        if statuses.get(user.clone()) == Some(WinStatus::Won) {
+            statuses.set(user.clone(), WinStatus::Lapsed);
+            lapsed.push_back(user);
+        }
+    }
+    save_win_statuses(env, utility_id, &statuses);
+    lapsed
+}
+
+/// Loads a drawn raffle whose claim deadline has passed, for its provider.
+fn load_expired_claims(env: &Env, utility_id: u64, sender: &Address) -> Utility {
+    sender.require_auth();
+    let u = UtilityContract::get_utility(env.clone(), utility_id);
+
+    if u.provider != *sender {
+        panic_with_error!(env, UtilityError::NotAuthorized)
+    }
+    if u.selection != Selection::Raffle || u.raffle.cancelled {
+        panic_with_error!(env, UtilityError::InvalidRaffleSelection)
+    }
+    if !u.raffle.ended {
+        panic_with_error!(env, UtilityError::RaffleNotEnded)
+    }
+    if !claims_closed(env, &u) {
+        panic_with_error!(env, UtilityError::ClaimWindowOpen)
+    }
+    u
+}
+
+/// Draws up to `count` new winners of `u` from `seed`, continuing the
+/// round numbering of earlier draws and skipping anyone already drawn.
+pub fn draw(env: &Env, u: &Utility, seed: &BytesN<32>, count: u64) -> Vec<Address> {
+    let mut statuses = win_statuses(env, u.id);
+    let mut entries = Vec::new(env);
+    let mut remaining = 0u64;
+    for entry in entrants(env, u.id).iter() {
+        if !statuses.contains_key(entry.user.clone()) {
+            remaining = remaining.saturating_add(entry.weight);
+            entries.push_back(entry);
+        }
+    }
+
+    let mut taken: Vec<bool> = Vec::new(env);
+    for _ in 0..entries.len() {
+        taken.push_back(false);
+    }
+
+    let first_round = rounds_drawn(env, u.id);
+    let rounds = count.min(entries.len() as u64) as u32;
+    let mut all = drawn(env, u.id);
+    let mut winners = Vec::new(env);
+    for round in first_round..first_round + rounds {
+        let mut point = draw_point(env, seed, round, remaining);
+        for (i, entry) in entries.iter().enumerate() {
+            let i = i as u32;
+            if taken.get_unchecked(i) {
+                continue;
+            }
+            if point < entry.weight {
+                taken.set(i, true);
+                remaining = remaining.saturating_sub(entry.weight);
+
+                statuses.set(entry.user.clone(), WinStatus::Won);
+                winners.push_back(entry.user.clone());
+                all.push_back(entry.user);
+                break;
+            }
+            point -= entry.weight;
+        }
+    }
+
+    save_win_statuses(env, u.id, &statuses);
+    env.storage()
+        .persistent()
+        .set(&(ROUNDS, u.id), &(first_round + rounds));
+    let key = (WINNERS, u.id);
+    env.storage().persistent().set(&key, &all);
+    config::extend_persistent(env, &key);
+    winners
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Chooses how entries to a raffle are weighted. Only possible before
+    /// anyone has joined.
+    pub fn set_entry_weight(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        weight: EntryWeight,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.selection != Selection::Raffle || u.raffle.ended {
+            panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
+        }
+        if entrant_count(&env, utility_id) > 0 {
+            panic_with_error!(&env, UtilityError::RaffleHasEntrants)
+        }
+        if let EntryWeight::TokenBalance(_, unit) = &weight {
+            if *unit <= 0 {
+                panic_with_error!(&env, UtilityError::InvalidConfig)
+            }
+        }
+
+        u.raffle.weight = weight;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Sets when entries open and close and how many entrants are accepted
+    /// (0 for `MAX_ENTRANTS`). `join_end_time` may not exceed `offer_expiry`.
+    pub fn set_raffle_window(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        start_time: u64,
+        join_end_time: u64,
+        max_entrants: u32,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.selection != Selection::Raffle || u.raffle.ended {
+            panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
+        }
+        if start_time > join_end_time || join_end_time > u.offer_expiry {
+            panic_with_error!(&env, UtilityError::InvalidTime)
+        }
+        if max_entrants > MAX_ENTRANTS
+            || (max_entrants > 0 && max_entrants < entrant_count(&env, utility_id))
+        {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        u.raffle.start_time = start_time;
+        u.raffle.join_end_time = join_end_time;
+        u.raffle.max_entrants = max_entrants;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Sets the last moment raffle winners can claim, 0 for no deadline.
+    /// It must fall after `offer_expiry`, when the draw can happen. Once
+    /// winners are drawn the deadline can only be extended.
+    pub fn set_claim_deadline(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        claim_deadline: u64,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.selection != Selection::Raffle || u.raffle.cancelled {
+            panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
+        }
+        if claim_deadline != 0 && claim_deadline <= u.offer_expiry {
+            panic_with_error!(&env, UtilityError::InvalidTime)
+        }
+        let extends = u.raffle.claim_deadline != 0
+            && (claim_deadline == 0 || claim_deadline >= u.raffle.claim_deadline);
+        if u.raffle.ended && !extends {
+            panic_with_error!(&env, UtilityError::InvalidTime)
+        }
+
+        u.raffle.claim_deadline = claim_deadline;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Replaces winners who missed the claim deadline with entrants drawn
+    /// from those not yet drawn, who get until `claim_deadline` to claim.
+    pub fn reroll_unclaimed(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        claim_deadline: u64,
+    ) -> Vec<Address> {
+        let mut u = load_expired_claims(&env, utility_id, &sender);
+        if claim_deadline <= env.ledger().timestamp() {
+            panic_with_error!(&env, UtilityError::InvalidTime)
+        }
+
+        let lapsed = lapse_unclaimed(&env, utility_id);
+        if lapsed.is_empty() {
+            panic_with_error!(&env, UtilityError::NoUnclaimedPrizes)
+        }
+
+        let seed = UtilityContract::get_raffle_seed(env.clone(), utility_id)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::RaffleNotEnded));
+        let winners = draw(&env, &u, &seed, lapsed.len() as u64);
+
+        u.raffle.claim_deadline = claim_deadline;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::raffle_rerolled(&env, utility_id, lapsed, winners.clone(), claim_deadline);
+        winners
+    }
+
+    /// Closes a raffle's prizes after the claim deadline, withdrawing any
+    /// unclaimed wins and returning what is left of the prize pool to the
+    /// provider. Minted rewards are simply not minted.
+    pub fn sweep_unclaimed(env: Env, utility_id: u64, sender: Address) -> i128 {
+        let mut u = load_expired_claims(&env, utility_id, &sender);
+
+        let lapsed = lapse_unclaimed(&env, utility_id);
+
+        let mut amount = u.reward.total_amount;
+        if lapsed.is_empty() && amount == 0 {
+            panic_with_error!(&env, UtilityError::NoUnclaimedPrizes)
+        }
+        if u.reward.receipt == Receipt::External {
+            amount = amount.min(escrow::balance(&env, utility_id));
+            escrow::release(
+                &env,
+                utility_id,
+                &reward_token(&env, &u),
+                &u.provider,
+                amount,
+            );
+        }
+
+        u.reward.total_amount = 0;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::prizes_swept(&env, utility_id, lapsed.len(), amount, u.provider);
+        amount
+    }
+
+    /// Grants `count` more tickets to `user` for raffles weighted by
+    /// `EntryWeight::Tickets`.
+    pub fn grant_tickets(env: Env, utility_id: u64, sender: Address, user: Address, count: u64) {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if has_entered(&env, utility_id, &user) {
+            panic_with_error!(&env, UtilityError::AlreadyJoined)
+        }
+
+        add_tickets(&env, utility_id, &user, count);
+    }
+
+    pub fn get_tickets(env: Env, utility_id: u64, user: Address) -> u64 {
+        tickets(&env, utility_id, &user)
+    }
+
+    pub fn get_entrant_count(env: Env, utility_id: u64) -> u32 {
+        entrant_count(&env, utility_id)
+    }
+
+    /// Entrants in join order starting at `cursor`, capped at the
+    /// configured page size.
+    pub fn list_entrants(env: Env, utility_id: u64, cursor: u32, limit: u32) -> Vec<RaffleEntry> {
+        let limit = limit.min(config::max_page_size(&env));
+        let entries = entrants(&env, utility_id);
+        let end = entries.len().min(cursor.saturating_add(limit));
+        if cursor >= end {
+            return Vec::new(&env);
+        }
+        entries.slice(cursor..end)
+    }
+
+    /// Current winners in draw order, leaving out lapsed ones.
+    pub fn get_raffle_winners(env: Env, utility_id: u64) -> Vec<Address> {
+        let statuses = win_statuses(&env, utility_id);
+        let mut winners = Vec::new(&env);
+        for user in drawn(&env, utility_id).iter() {
+            if matches!(
+                statuses.get(user.clone()),
+                Some(WinStatus::Won | WinStatus::Claimed)
+            ) {
+                winners.push_back(user);
+            }
+        }
+        winners
+    }
+
+    /// Seed the winners were drawn from, once the raffle has ended.
+    pub fn get_raffle_seed(env: Env, utility_id: u64) -> Option<BytesN<32>> {
+        env.storage()
+            .persistent()
+            .get::<(Symbol, u64), BytesN<32>>(&(SEED, utility_id))
+    }
+
+    pub fn is_raffle_winner(env: Env, utility_id: u64, user: Address) -> bool {
+        is_winner(&env, utility_id, &user)
+    }
+}
@@ -0,0 +1,81 @@
+//! Proof-of-redemption receipts.
+//!
+//! A provider may point a utility at a receipt NFT contract, for which this
+//! contract must be an appointed minter. Every redemption then mints a
+//! non-transferable receipt to the holder, and `EligibilityRule::HasReceipt`
+//! lets other utilities require one.
+
+use soroban_sdk::{
+    contractclient, contractimpl, panic_with_error, symbol_short, Address, Env, Symbol,
+};
+
+use crate::structs::{Utility, UtilityError};
+use crate::{config, events, UtilityContract, UtilityContractClient};
+
+pub const RECEIPT_NFT: Symbol = symbol_short!("RCPT_NFT");
+
+/// The part of the receipt NFT interface this contract calls.
+#[allow(dead_code)]
+#[contractclient(name = "ReceiptNftClient")]
+pub trait ReceiptNftInterface {
+    fn mint(env: Env, minter: Address, to: Address, utility_id: u64) -> u64;
+    fn has_receipt(env: Env, owner: Address, issuer: Address, utility_id: u64) -> bool;
+}
+
+pub fn receipt_nft(env: &Env, utility_id: u64) -> Option<Address> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Address>(&(RECEIPT_NFT, utility_id))
+}
+
+/// Mints a receipt for `holder` redeeming `u`, if `u` issues receipts.
+pub fn issue(env: &Env, u: &Utility, holder: &Address) {
+    let Some(contract) = receipt_nft(env, u.id) else {
+        return;
+    };
+    let token_id =
+        ReceiptNftClient::new(env, &contract).mint(&env.current_contract_address(), holder, &u.id);
+
+    events::receipt_issued(env, u.id, holder.clone(), contract, token_id);
+}
+
+/// Whether `user` holds a receipt this contract issued for `utility_id`.
+pub fn has_receipt(env: &Env, contract: &Address, user: &Address, utility_id: u64) -> bool {
+    ReceiptNftClient::new(env, contract).has_receipt(
+        user,
+        &env.current_contract_address(),
+        &utility_id,
+    )
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Mints a receipt from `receipt_nft` on every redemption of the
+    /// utility, or stops minting when `None`.
+    pub fn set_receipt_nft(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        receipt_nft: Option<Address>,
+    ) {
+        sender.require_auth();
+        if Self::get_utility(env.clone(), utility_id).provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        let key = (RECEIPT_NFT, utility_id);
+        match &receipt_nft {
+            Some(contract) => {
+                env.storage().persistent().set(&key, contract);
+                config::extend_persistent(&env, &key);
+            }
+            None => env.storage().persistent().remove(&key),
+        }
+
+        events::receipt_nft_set(&env, utility_id, receipt_nft);
+    }
+
+    pub fn get_receipt_nft(env: Env, utility_id: u64) -> Option<Address> {
+        receipt_nft(&env, utility_id)
+    }
+}
@@ -0,0 +1,189 @@
+//! Pay-per-redemption partner settlement.
+//!
+//! For B2B deals the provider funds an escrow and the partner is paid
+//! `amount_per_redemption` of the settlement token out of it on every
+//! redemption, in the same transaction. When the escrow can no longer cover
+//! the next redemption the utility pauses itself; funding it back up
+//! resumes a utility paused that way.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol};
+
+use crate::structs::{RewardError, Settlement, Utility, UtilityError};
+use crate::{config, events, storage, UtilityContract, UtilityContractClient};
+
+pub const SETTLEMENT: Symbol = symbol_short!("SETTLE");
+
+pub fn load(env: &Env, utility_id: u64) -> Option<Settlement> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Settlement>(&(SETTLEMENT, utility_id))
+}
+
+fn save(env: &Env, utility_id: u64, settlement: &Settlement) {
+    let key = (SETTLEMENT, utility_id);
+    env.storage().persistent().set(&key, settlement);
+    config::extend_persistent(env, &key);
+}
+
+fn read(env: &Env, utility_id: u64) -> Settlement {
+    load(env, utility_id).unwrap_or_else(|| panic_with_error!(env, RewardError::NoSettlement))
+}
+
+/// Pays `u`'s partner for one redemption, pausing `u` once the escrow
+/// cannot cover another.
+pub fn settle(env: &Env, u: &Utility) {
+    let Some(mut s) = load(env, u.id) else {
+        return;
+    };
+    if s.balance < s.amount_per_redemption {
+        panic_with_error!(env, RewardError::InsufficientEscrow)
+    }
+
+    s.balance -= s.amount_per_redemption;
+    s.redemptions += 1;
+    s.total_paid = s
+        .total_paid
+        .checked_add(s.amount_per_redemption)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    token::TokenClient::new(env, &s.token).transfer(
+        &env.current_contract_address(),
+        &u.partner,
+        &s.amount_per_redemption,
+    );
+    events::partner_settled(
+        env,
+        u.id,
+        u.partner.clone(),
+        s.token.clone(),
+        s.amount_per_redemption,
+        s.balance,
+    );
+
+    pause_if_low(env, u, &mut s);
+    save(env, u.id, &s);
+}
+
+/// Pauses `u` when the escrow can no longer cover a redemption. A utility
+/// the provider already paused is left for the provider to resume.
+fn pause_if_low(env: &Env, u: &Utility, s: &mut Settlement) {
+    if s.balance >= s.amount_per_redemption || s.low_balance || u.paused {
+        return;
+    }
+    s.low_balance = true;
+    let mut paused = u.clone();
+    paused.paused = true;
+    storage::save_utility(env, u.id, &paused);
+    events::utility_paused(env, u.id, true, env.current_contract_address());
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Pays the partner `amount_per_redemption` of `token_address` from the
+    /// settlement escrow on every redemption. The token cannot change while
+    /// the escrow holds funds. The utility pauses if the escrow does not
+    /// cover the new amount.
+    pub fn set_settlement(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        token_address: Address,
+        amount_per_redemption: i128,
+    ) -> Settlement {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if amount_per_redemption <= 0 {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        let mut s = match load(&env, utility_id) {
+            Some(s) if s.balance > 0 && s.token != token_address => {
+                panic_with_error!(&env, UtilityError::InvalidConfig)
+            }
+            Some(s) => Settlement {
+                token: token_address,
+                amount_per_redemption,
+                ..s
+            },
+            None => Settlement {
+                token: token_address,
+                amount_per_redemption,
+                balance: 0,
+                redemptions: 0,
+                total_paid: 0,
+                low_balance: false,
+            },
+        };
+        pause_if_low(&env, &u, &mut s);
+        save(&env, utility_id, &s);
+
+        events::settlement_updated(&env, utility_id, s.token.clone(), amount_per_redemption);
+        s
+    }
+
+    /// Adds `amount` to the settlement escrow. A utility paused for a low
+    /// balance resumes once the escrow covers a redemption again.
+    pub fn fund_settlement(env: Env, utility_id: u64, from: Address, amount: i128) -> Settlement {
+        from.require_auth();
+        let mut s = read(&env, utility_id);
+        if amount <= 0 {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        token::TokenClient::new(&env, &s.token).transfer(
+            &from,
+            &env.current_contract_address(),
+            &amount,
+        );
+        s.balance = s
+            .balance
+            .checked_add(amount)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::ArithmeticOverflow));
+
+        if s.low_balance && s.balance >= s.amount_per_redemption {
+            s.low_balance = false;
+            let mut u = Self::get_utility(env.clone(), utility_id);
+            if u.cancelled_at == 0 {
+                u.paused = false;
+                storage::save_utility(&env, utility_id, &u);
+                events::utility_paused(&env, utility_id, false, env.current_contract_address());
+            }
+        }
+        save(&env, utility_id, &s);
+
+        events::settlement_funded(&env, utility_id, from, amount, s.balance);
+        s
+    }
+
+    /// Returns `amount` of unspent escrow to the provider, pausing the
+    /// utility if what is left cannot cover a redemption.
+    pub fn withdraw_settlement(env: Env, utility_id: u64, sender: Address, amount: i128) {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        let mut s = read(&env, utility_id);
+        if amount <= 0 || amount > s.balance {
+            panic_with_error!(&env, RewardError::InsufficientEscrow)
+        }
+
+        s.balance -= amount;
+        pause_if_low(&env, &u, &mut s);
+        save(&env, utility_id, &s);
+        token::TokenClient::new(&env, &s.token).transfer(
+            &env.current_contract_address(),
+            &sender,
+            &amount,
+        );
+
+        events::settlement_withdrawn(&env, utility_id, sender, amount, s.balance);
+    }
+
+    /// Settlement terms, escrow balance and totals paid to the partner.
+    pub fn settlement_report(env: Env, utility_id: u64) -> Settlement {
+        read(&env, utility_id)
+    }
+}
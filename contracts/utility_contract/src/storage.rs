@@ -0,0 +1,103 @@
+//! Persistent storage layout for utilities.
+//!
+//! Each utility lives under its own `(U_STORAGE, id)` entry so reads stay
+//! bounded as the catalogue grows. Provider and partner lookups go through
+//! append-only secondary indexes of the form `(kind, address, position) -> id`
+//! with a per-address length under `(kind_count, address)`.
+//!
+//! Per-NFT state created by `claim_utitlity_on_nft` is kept under
+//! `(TOKEN_UTILITY, token, utility_id)` and indexed per token the same way.
+
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+use crate::config;
+use crate::structs::{TokenUtility, Utility};
+
+pub const U_STORAGE: Symbol = symbol_short!("U_STORAGE");
+pub const U_COUNT: Symbol = symbol_short!("U_COUNT");
+pub const BY_PROVIDER: Symbol = symbol_short!("BY_PROV");
+pub const BY_PROVIDER_COUNT: Symbol = symbol_short!("PROV_CNT");
+pub const BY_PARTNER: Symbol = symbol_short!("BY_PART");
+pub const BY_PARTNER_COUNT: Symbol = symbol_short!("PART_CNT");
+pub const TOKEN_UTILITY: Symbol = symbol_short!("TOKEN_U");
+pub const BY_TOKEN: Symbol = symbol_short!("BY_TOKEN");
+pub const BY_TOKEN_COUNT: Symbol = symbol_short!("TOKEN_CNT");
+
+pub fn utility_count(env: &Env) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<Symbol, u64>(&U_COUNT)
+        .unwrap_or(0)
+}
+
+pub fn load_utility(env: &Env, utility_id: u64) -> Option<Utility> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), Utility>(&(U_STORAGE, utility_id))
+}
+
+pub fn save_utility(env: &Env, utility_id: u64, u: &Utility) {
+    let key = (U_STORAGE, utility_id);
+    env.storage().persistent().set(&key, u);
+    config::extend_persistent(env, &key);
+}
+
+/// Stores a new utility under `u.id` and appends it to the provider and
+/// partner indexes. `u.id` must equal the current [`utility_count`].
+pub fn add_utility(env: &Env, u: &Utility) {
+    save_utility(env, u.id, u);
+    env.storage().persistent().set(&U_COUNT, &(u.id + 1));
+
+    push_index(env, BY_PROVIDER, BY_PROVIDER_COUNT, &u.provider, u.id);
+    push_index(env, BY_PARTNER, BY_PARTNER_COUNT, &u.partner, u.id);
+}
+
+pub fn load_token_utility(env: &Env, token: &Address, utility_id: u64) -> Option<TokenUtility> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address, u64), TokenUtility>(&(TOKEN_UTILITY, token.clone(), utility_id))
+}
+
+/// Stores per-NFT state, adding the utility to the token's index the first
+/// time it is bound.
+pub fn save_token_utility(env: &Env, token_utility: &TokenUtility) {
+    let key = (
+        TOKEN_UTILITY,
+        token_utility.token.clone(),
+        token_utility.utility_id,
+    );
+    if !env.storage().persistent().has(&key) {
+        push_index(
+            env,
+            BY_TOKEN,
+            BY_TOKEN_COUNT,
+            &token_utility.token,
+            token_utility.utility_id,
+        );
+    }
+    env.storage().persistent().set(&key, token_utility);
+    config::extend_persistent(env, &key);
+}
+
+pub fn index_len(env: &Env, count_key: Symbol, owner: &Address) -> u64 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address), u64>(&(count_key, owner.clone()))
+        .unwrap_or(0)
+}
+
+pub fn index_get(env: &Env, key: Symbol, owner: &Address, position: u64) -> Option<u64> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, Address, u64), u64>(&(key, owner.clone(), position))
+}
+
+fn push_index(env: &Env, key: Symbol, count_key: Symbol, owner: &Address, utility_id: u64) {
+    let len = index_len(env, count_key.clone(), owner);
+    env.storage()
+        .persistent()
+        .set(&(key, owner.clone(), len), &utility_id);
+    env.storage()
+        .persistent()
+        .set(&(count_key, owner.clone()), &(len + 1));
+}
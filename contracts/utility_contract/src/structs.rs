@@ -1,8 +1,8 @@
-use soroban_sdk::{contracterror, contracttype, Address, String, Vec};
+use soroban_sdk::{contracterror, contracttype, Address, String, Symbol, Vec};
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ExpiryType {
     None = 0,
     TimeBased = 1,
@@ -11,7 +11,7 @@ pub enum ExpiryType {
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UtilityType {
     NftBenefit = 0,
     Giveaway = 1,
@@ -19,7 +19,7 @@ pub enum UtilityType {
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UsageType {
     Unlimited = 0,
     Limited = 1,
@@ -27,7 +27,7 @@ pub enum UsageType {
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EligibleType {
     None = 0,
     NftOwner = 1,
@@ -36,7 +36,7 @@ pub enum EligibleType {
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Selection {
     All = 0,
     Raffle = 1,
@@ -44,17 +44,90 @@ pub enum Selection {
 
 #[repr(u32)]
 #[contracttype]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Receipt {
     None = 0,
     MintToken = 1,
     External = 2,
     HTSToken = 3,
+    /// Mints loyalty points from the points token in `token_addresses[0]`.
+    Points = 4,
+}
+
+/// What a new holder of an NFT inherits from the previous holder's claim.
+#[repr(u32)]
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferPolicy {
+    /// Usage and expiry are granted afresh to the new holder.
+    ResetOnTransfer = 0,
+    /// Remaining usage and expiry move with the NFT.
+    CarryOver = 1,
+    /// Only the holder at claim time may redeem.
+    Soulbound = 2,
+}
+
+/// A node in a utility's eligibility rule tree. `Not` wraps exactly one
+/// rule; `OnWhitelist` refers to a named list kept per utility;
+/// `HasReceipt(receipt_nft, utility_id)` requires a redemption receipt this
+/// contract issued for another utility; `MarkedEligible` admits users the
+/// admin flagged with `mark_eligible`.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EligibilityRule {
+    AnyOf(Vec<EligibilityRule>),
+    AllOf(Vec<EligibilityRule>),
+    Not(Vec<EligibilityRule>),
+    OwnsCollection(Vec<Address>),
+    MinTokenBalance(Address, i128),
+    OnWhitelist(Symbol),
+    HeldSince(Address, u64),
+    HasReceipt(Address, u64),
+    MarkedEligible,
+}
+
+#[repr(u32)]
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProviderMode {
+    Open = 0,
+    Allowlist = 1,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub admin: Address,
+    pub fee_token: Option<Address>,
+    pub creation_fee: i128,
+    pub platform_fee_bps: u32,
+    pub ttl_threshold: u32,
+    pub ttl_extend_to: u32,
+    pub max_page_size: u32,
+    pub max_batch_size: u32,
+    pub provider_mode: ProviderMode,
+    pub schema_version: u32,
+}
+
+/// A single field change applied by `set_config`.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigUpdate {
+    Admin(Address),
+    FeeToken(Address),
+    CreationFee(i128),
+    PlatformFeeBps(u32),
+    /// `(threshold, extend_to)` in ledgers.
+    Ttl(u32, u32),
+    MaxPageSize(u32),
+    MaxBatchSize(u32),
+    ProviderMode(ProviderMode),
 }
 
 #[contracttype]
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Utility {
+    pub id: u64,
     pub provider: Address,
     pub expiry: u64,
     pub usage: u64,
@@ -66,24 +139,283 @@ pub struct Utility {
     pub raffle: Raffle,
     pub selection: Selection,
     pub reward: Reward,
+    pub paused: bool,
+    pub transfer_policy: TransferPolicy,
+    pub collections: Vec<Address>,
+    pub min_holding_period: u64,
+    pub utility_type: UtilityType,
+    /// When the provider cancelled the utility, 0 while it is live.
+    pub cancelled_at: u64,
+}
+
+/// Cap on giveaway claims per fixed window of `window` seconds.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub window: u64,
+    pub max_per_window: u32,
+}
+
+/// When a holder was first seen holding an NFT, and how many they held.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HolderRecord {
+    pub since: u64,
+    pub balance: i128,
+}
+
+/// Permission for `delegate` to redeem a utility on an NFT held by `owner`.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delegation {
+    pub token: Address,
+    pub utility_id: u64,
+    pub owner: Address,
+    pub delegate: Address,
+    pub expires_at: u64,
+    pub max_uses: u64,
+    pub uses: u64,
+}
+
+/// Per-NFT copy of the usage and expiry a utility grants once it has been
+/// claimed on a token.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenUtility {
+    pub token: Address,
+    pub utility_id: u64,
+    pub owner: Address,
+    pub usage: u64,
+    pub expiry: u64,
+    pub claimed_at: u64,
+}
+
+#[repr(u32)]
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RaffleStatus {
+    NotRaffle = 0,
+    Upcoming = 1,
+    Open = 2,
+    Ended = 3,
+    Cancelled = 4,
+}
+
+#[repr(u32)]
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusReason {
+    Redeemable = 0,
+    NotClaimed = 1,
+    Paused = 2,
+    Expired = 3,
+    UsageExhausted = 4,
+}
+
+/// Result of `utility_status`. `remaining_usages` is `None` for unlimited
+/// utilities and `expires_at` is `None` when the utility never expires.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityStatusReport {
+    pub utility_id: u64,
+    pub token: Address,
+    pub remaining_usages: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub redeemable: bool,
+    pub reason: StatusReason,
+}
+
+/// One utility bound to an NFT held by the queried user.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserBenefit {
+    pub token: Address,
+    pub utility_id: u64,
+    pub utility_uri: String,
+    pub usage_type: UsageType,
+    pub usage_left: u64,
+    pub expiry: u64,
+    pub redeemable: bool,
+    pub reward_claimed: bool,
+    pub raffle_status: RaffleStatus,
+    pub eligible: bool,
 }
 
 #[contracttype]
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtilityPage {
+    pub utilities: Vec<Utility>,
+    pub next_cursor: Option<u64>,
+}
+
+/// Amounts are in the reward token's smallest unit; `decimals` is read
+/// from the token at creation so clients can display them.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Reward {
     pub receipt: Receipt,
     pub token_addresses: Vec<Address>,
-    pub total_amount: u64,
-    pub amount_per_win: u64,
+    pub total_amount: i128,
+    pub amount_per_win: i128,
     pub no_of_winners: u64,
+    pub decimals: u32,
+    pub vesting: Vesting,
+}
+
+/// How a won reward unlocks, measured from the moment it is claimed.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Vesting {
+    /// Paid out in full on claim.
+    None,
+    /// Unlocks linearly over `duration` seconds, with nothing withdrawable
+    /// before `cliff`: `Linear(cliff, duration)`.
+    Linear(u64, u64),
+    /// Unlocks in full after the given number of seconds.
+    Cliff(u64),
+}
+
+/// Points minted to users of a `Receipt::Points` utility for taking part,
+/// on top of any reward they win.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointsAward {
+    pub per_redemption: i128,
+    pub per_entry: i128,
+}
+
+/// What a catalog item hands out when redeemed for points.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CatalogReward {
+    /// `Token(token, amount)`, paid from stock the provider escrows.
+    Token(Address, i128),
+    /// `Usages(utility_id, count)` extra usages on an NFT the user holds.
+    Usages(u64, u64),
+    /// `RaffleTickets(utility_id, count)` in a ticket-weighted raffle.
+    RaffleTickets(u64, u64),
+}
+
+/// A provider-listed item users buy with points.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogItem {
+    pub id: u64,
+    pub provider: Address,
+    pub points_token: Address,
+    pub price: i128,
+    pub stock: u32,
+    pub redeemed: u32,
+    pub reward: CatalogReward,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogPage {
+    pub items: Vec<CatalogItem>,
+    pub next_cursor: Option<u64>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogPurchase {
+    pub user: Address,
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CouponInventory {
+    pub total: u32,
+    pub assigned: u32,
+}
+
+/// Pay-per-redemption terms with a partner and the state of their escrow.
+/// `low_balance` is set while the utility is paused for lack of funds.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settlement {
+    pub token: Address,
+    pub amount_per_redemption: i128,
+    pub balance: i128,
+    pub redemptions: u64,
+    pub total_paid: i128,
+    pub low_balance: bool,
+}
+
+/// Oracle terms for a reward priced in a reference currency.
+/// `reference_amount` is the value of one win in the oracle's quote
+/// currency and precision; `last_price` is the last price accepted from
+/// `oracle`.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PricedReward {
+    pub oracle: Address,
+    pub reference_amount: i128,
+    pub max_age: u64,
+    pub max_deviation_bps: u32,
+    pub max_payout: i128,
+    pub last_price: i128,
+}
+
+/// A winner's vesting reward allocation.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VestingGrant {
+    pub token: Address,
+    pub total: i128,
+    pub withdrawn: i128,
+    pub start: u64,
+    pub vesting: Vesting,
 }
 
 #[contracttype]
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Raffle {
     pub start_time: u64,
     pub ended: bool,
     // pub winners_merkle: Bytes,
+    pub weight: EntryWeight,
+    pub cancelled: bool,
+    /// Last moment entries are accepted; no later than `offer_expiry`.
+    pub join_end_time: u64,
+    /// Cap on the number of entrants, 0 for no cap.
+    pub max_entrants: u32,
+    /// Last moment winners can claim, 0 for no deadline.
+    pub claim_deadline: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicketSale {
+    pub token: Address,
+    pub price: i128,
+    pub max_per_user: u64,
+    /// Entrants needed for the draw to go ahead; below it the raffle is
+    /// cancelled and tickets refunded.
+    pub min_entrants: u32,
+}
+
+/// How many chances an entrant gets, fixed when they join.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryWeight {
+    /// One chance per entrant.
+    Equal,
+    /// One chance per NFT held in the collection.
+    NftCount(Address),
+    /// One chance per whole `unit` of the token held.
+    TokenBalance(Address, i128),
+    /// One chance per ticket granted to the entrant.
+    Tickets,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleEntry {
+    pub user: Address,
+    pub weight: u64,
 }
 
 #[contracterror]
@@ -104,4 +436,64 @@ pub enum UtilityError {
     UtilityNotFound = 12,
     UtilityExpired = 13,
     UsageExceeded = 14,
+    UtilityPaused = 15,
+    InvalidSelection = 16,
+    InvalidReceipt = 17,
+    InvalidUsage = 18,
+    NotInitialized = 19,
+    MissingParameter = 20,
+    UtilityNotClaimed = 21,
+    AlreadyInitialized = 22,
+    InvalidConfig = 23,
+    ProviderNotAllowed = 24,
+    DelegationActive = 25,
+    DelegationNotFound = 26,
+    InvalidDelegation = 27,
+    SoulboundUtility = 28,
+    HoldingPeriodNotMet = 29,
+    BatchTooLarge = 30,
+    NotEligible = 31,
+    InvalidRule = 32,
+    AlreadyJoined = 33,
+    NoEntryWeight = 34,
+    RaffleHasEntrants = 35,
+    NotWinner = 36,
+    NoTicketSale = 37,
+    TicketCapExceeded = 38,
+    RefundUnavailable = 39,
+    NothingToRefund = 40,
+    ArithmeticOverflow = 41,
+    RaffleNotStarted = 42,
+    RaffleFull = 43,
+    ClaimDeadlinePassed = 44,
+    ClaimWindowOpen = 45,
+    NoUnclaimedPrizes = 46,
+    RateLimited = 47,
+    UtilityCancelled = 48,
+    UtilityNotCancelled = 49,
+}
+
+/// Errors from reward payouts. A contract error enum is limited to 50
+/// variants, so these are numbered from 100 to stay apart from
+/// `UtilityError`.
+#[contracterror]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RewardError {
+    InvalidVesting = 100,
+    NoVestingGrant = 101,
+    NothingToWithdraw = 102,
+    ItemNotFound = 103,
+    OutOfStock = 104,
+    InvalidCatalogItem = 105,
+    InvalidClaimCode = 106,
+    ClaimCodeUsed = 107,
+    ClaimCodeNotCommitted = 108,
+    InsufficientFees = 109,
+    NoSettlement = 110,
+    InsufficientEscrow = 111,
+    InvalidRewardAmount = 112,
+    StalePrice = 113,
+    PriceDeviation = 114,
+    InvalidPrice = 115,
 }
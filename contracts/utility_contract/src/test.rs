@@ -1,23 +1,100 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{vec, Address, Env};
-use structs::{Raffle, Reward};
+use events::{
+    CatalogItemRedeemed, EligibleMarked, PartnerShareSet, PointsAwardSet, PointsAwarded,
+    PrizesSwept, RaffleJoined, RaffleRerolled, RateLimitSet, ReceiptIssued, ReceiptNftSet,
+    RewardPricingSet, RewardReclaimed, SettlementUpdated, SettlementWithdrawn, UtilityCreated,
+    UtilityPaused, UtilityUpdated, EVENT_VERSION,
+};
+use pricing::{Asset, PriceData};
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, Events, Ledger},
+    vec, Address, Bytes, BytesN, Env, IntoVal, Val,
+};
+use structs::{
+    CatalogPurchase, CatalogReward, Config, ConfigUpdate, CouponInventory, EligibilityRule,
+    EntryWeight, PointsAward, ProviderMode, Raffle, RaffleEntry, RaffleStatus, Reward, RewardError,
+    Settlement, StatusReason, UtilityStatusReport, UtilityType, Vesting, VestingGrant,
+};
+
+fn last_event(env: &Env) -> Vec<(Address, Vec<Val>, Val)> {
+    let all = env.events().all();
+    all.slice(all.len() - 1..)
+}
+
+fn create_nft(env: &Env, holder: &Address) -> Address {
+    let token_address = env
+        .register_stellar_asset_contract_v2(Address::generate(env))
+        .address();
+    token::StellarAssetClient::new(env, &token_address).mint(holder, &1);
+    token_address
+}
+
+fn setup(env: &Env) -> (UtilityContractClient<'_>, Address) {
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(env, &contract_id);
+    let admin = Address::generate(env);
+    client.setup_config(&admin);
+    (client, admin)
+}
+
+fn assert_error<T: core::fmt::Debug, E: core::fmt::Debug>(
+    result: Result<Result<T, E>, Result<soroban_sdk::Error, soroban_sdk::InvokeError>>,
+    error: impl Into<soroban_sdk::Error>,
+) {
+    assert_eq!(result.unwrap_err(), Ok(error.into()));
+}
+
+fn create_utility(
+    env: &Env,
+    client: &UtilityContractClient,
+    provider: &Address,
+    partner: &Address,
+) -> Utility {
+    client.create_new_utility(
+        &vec![env, provider.to_string(), partner.to_string()],
+        &String::from_str(env, "https://example.com"),
+        &vec![
+            env,
+            1,
+            env.ledger().timestamp() + 1000,
+            env.ledger().timestamp() + 2000,
+        ],
+        &vec![env, 1, 10],
+        &(env.ledger().timestamp() + 500),
+        &1,
+        &0,
+        &vec![env],
+        &vec![env, 1000, 100, 10],
+    )
+}
 
 #[test]
 fn test_setup_config() {
     let env = Env::default();
+    env.mock_all_auths();
     let contract_id = env.register_contract(None, UtilityContract);
     let client = UtilityContractClient::new(&env, &contract_id);
 
-    let admin = &String::from_str(
+    let admin = Address::from_string(&String::from_str(
         &env,
         "GAIIG7PGQYFG4AZK36YWKE3GPH6GXU4BIDZZASMP5DSVZL2DZ75W34JS",
-    );
+    ));
 
     client.setup_config(&admin);
 
-    assert_eq!(&client.get_config(), admin);
+    let config = client.get_config();
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.provider_mode, ProviderMode::Open);
+    assert_eq!(config.schema_version, config::SCHEMA_VERSION);
+    assert_eq!(config.max_page_size, config::DEFAULT_MAX_PAGE_SIZE);
+    assert_error(
+        client.try_setup_config(&Address::generate(&env)),
+        UtilityError::AlreadyInitialized,
+    );
 }
 
 #[test]
@@ -26,33 +103,119 @@ pub fn test_config() {
     let contract_id = env.register_contract(None, UtilityContract);
     let client = UtilityContractClient::new(&env, &contract_id);
 
-    let config = client.get_config();
+    let config = client.try_get_config();
 
-    assert_eq!(config, String::from_str(&env, &"No Admin Set"))
+    assert_error(config, UtilityError::NotInitialized);
 }
 
 #[test]
-pub fn test_nft_ownership() {
+fn test_set_config() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, UtilityContract);
-    let client = UtilityContractClient::new(&env, &contract_id);
+    let (client, admin) = setup(&env);
+    let new_admin = Address::generate(&env);
+    let fee_token = Address::generate(&env);
 
-    let token_address_string = String::from_str(
-        &env,
-        "GBT4VVTDPCNA45MNWX5G6LUTLIEENSTUHDVXO2AQHAZ24KUZUPLPGJZH",
+    client.set_config(&admin, &ConfigUpdate::FeeToken(fee_token.clone()));
+    client.set_config(&admin, &ConfigUpdate::CreationFee(25));
+    client.set_config(&admin, &ConfigUpdate::PlatformFeeBps(250));
+    client.set_config(&admin, &ConfigUpdate::Ttl(100, 1000));
+    client.set_config(&admin, &ConfigUpdate::MaxPageSize(2));
+    client.set_config(&admin, &ConfigUpdate::MaxBatchSize(5));
+    let config = client.set_config(&admin, &ConfigUpdate::Admin(new_admin.clone()));
+
+    assert_eq!(
+        config,
+        Config {
+            admin: new_admin.clone(),
+            fee_token: Some(fee_token),
+            creation_fee: 25,
+            platform_fee_bps: 250,
+            ttl_threshold: 100,
+            ttl_extend_to: 1000,
+            max_page_size: 2,
+            max_batch_size: 5,
+            provider_mode: ProviderMode::Open,
+            schema_version: config::SCHEMA_VERSION,
+        }
     );
+    assert_eq!(client.get_config(), config);
 
-    let user_address_string = String::from_str(
-        &env,
-        "GDQOSTXBBGFO5N26DOGBIDDOLSSYJPA7DNCQ2V46B5D24JYIH2H3B54H",
+    assert_error(
+        client.try_set_config(&admin, &ConfigUpdate::MaxPageSize(10)),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_set_config(&new_admin, &ConfigUpdate::PlatformFeeBps(10_001)),
+        UtilityError::InvalidConfig,
+    );
+    assert_error(
+        client.try_set_config(&new_admin, &ConfigUpdate::Ttl(1000, 100)),
+        UtilityError::InvalidConfig,
+    );
+    assert_error(
+        client.try_set_config(&new_admin, &ConfigUpdate::MaxPageSize(0)),
+        UtilityError::InvalidConfig,
+    );
+
+    // The configured page size now bounds list calls.
+    client.set_config(&new_admin, &ConfigUpdate::CreationFee(0));
+    for _ in 0..3 {
+        create_utility(&env, &client, &admin, &admin);
+    }
+    assert_eq!(client.list_utilities(&0, &10).utilities.len(), 2);
+}
+
+#[test]
+fn test_provider_allowlist() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let provider = Address::generate(&env);
+
+    client.set_config(&admin, &ConfigUpdate::ProviderMode(ProviderMode::Allowlist));
+    assert!(!client.is_provider_allowed(&provider));
+    assert_error(
+        try_create(
+            &env,
+            &client,
+            &provider,
+            vec![&env, 1, 1000, 2000],
+            vec![&env, 1, 10],
+            500,
+            0,
+            0,
+            vec![&env],
+            vec![&env, 1000, 100, 10],
+        ),
+        UtilityError::ProviderNotAllowed,
     );
 
-    let result = client.check_nft_ownership(
-        &Address::from_string(&token_address_string),
-        &Address::from_string(&user_address_string),
+    client.set_provider_allowed(&admin, &provider, &true);
+    assert!(client.is_provider_allowed(&provider));
+    create_utility(&env, &client, &provider, &provider);
+
+    assert_error(
+        client.try_set_provider_allowed(&provider, &provider, &false),
+        UtilityError::NotAuthorized,
     );
+}
+
+#[test]
+pub fn test_nft_ownership() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let issuer = Address::generate(&env);
+    let token_address = env.register_stellar_asset_contract_v2(issuer).address();
+    let user = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token_address).mint(&user, &1);
+
+    let result = client.check_nft_ownership(&token_address, &user);
 
-    assert_eq!(result, true);
+    assert!(result);
+    assert!(!client.check_nft_ownership(&token_address, &Address::generate(&env)));
 }
 
 #[test]
@@ -71,6 +234,7 @@ pub fn test_create_utility() {
     ));
 
     let utility = Utility {
+        id: 0,
         provider: provider.clone(),
         expiry: env.ledger().timestamp() + 1000,
         usage: 10,
@@ -83,6 +247,11 @@ pub fn test_create_utility() {
             start_time: env.ledger().timestamp() + 500,
             ended: false,
             // winners_merkle: Bytes::from_array(&env, &[0u8; 32]),
+            weight: EntryWeight::Equal,
+            cancelled: false,
+            join_end_time: 2000,
+            max_entrants: 0,
+            claim_deadline: 0,
         },
         selection: Selection::All,
         reward: Reward {
@@ -91,14 +260,255 @@ pub fn test_create_utility() {
             total_amount: 1000,
             amount_per_win: 100,
             no_of_winners: 10,
+            decimals: 0,
+            vesting: Vesting::None,
         },
+        paused: false,
+        transfer_policy: TransferPolicy::CarryOver,
+        collections: vec![&env],
+        min_holding_period: 0,
+        utility_type: UtilityType::NftBenefit,
+        cancelled_at: 0,
     };
 
-    // client.create_new_utility(&utility, &provider);
+    client.create_new_utility(
+        &vec![&env, provider.to_string(), utility.partner.to_string()],
+        &utility.utility_uri,
+        &vec![&env, 1, utility.expiry, utility.offer_expiry],
+        &vec![&env, 1, utility.usage],
+        &utility.raffle.start_time,
+        &0,
+        &0,
+        &vec![&env],
+        &vec![&env, 1000, 100, 10],
+    );
 
     // Check if the utility was created
-    let created_utility = client.get_utility(&1);
+    let created_utility = client.get_utility(&0);
     assert_eq!(created_utility.provider, provider);
+    assert_eq!(created_utility, utility);
+}
+
+#[test]
+fn test_create_utility_emits_event() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider = Address::generate(&env);
+
+    create_utility(&env, &client, &provider, &provider);
+    let u = create_utility(&env, &client, &provider, &provider);
+
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (
+                    symbol_short!("utility"),
+                    symbol_short!("created"),
+                    EVENT_VERSION,
+                    1_u64
+                )
+                    .into_val(&env),
+                UtilityCreated {
+                    provider: provider.clone(),
+                    partner: provider,
+                    utility_uri: u.utility_uri,
+                    offer_expiry: u.offer_expiry,
+                    selection: Selection::Raffle,
+                }
+                .into_val(&env),
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_list_utilities() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider = Address::generate(&env);
+    for _ in 0..3 {
+        create_utility(&env, &client, &provider, &provider);
+    }
+
+    let page = client.list_utilities(&0, &2);
+    assert_eq!(page.utilities.len(), 2);
+    assert_eq!(page.utilities.get(1).unwrap().id, 1);
+    assert_eq!(page.next_cursor, Some(2));
+
+    let page = client.list_utilities(&2, &2);
+    assert_eq!(page.utilities.len(), 1);
+    assert_eq!(page.utilities.get(0).unwrap().id, 2);
+    assert_eq!(page.next_cursor, None);
+
+    let page = client.list_utilities(&0, &1000);
+    assert_eq!(page.utilities.len(), 3);
+}
+
+#[test]
+fn test_list_utilities_by_provider_and_partner() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider_a = Address::generate(&env);
+    let provider_b = Address::generate(&env);
+    let partner = Address::generate(&env);
+
+    create_utility(&env, &client, &provider_a, &partner);
+    create_utility(&env, &client, &provider_b, &provider_b);
+    create_utility(&env, &client, &provider_a, &provider_a);
+    create_utility(&env, &client, &provider_b, &partner);
+
+    let page = client.list_utilities_by_provider(&provider_a, &0, &10);
+    assert_eq!(page.utilities.len(), 2);
+    assert_eq!(page.utilities.get(0).unwrap().id, 0);
+    assert_eq!(page.utilities.get(1).unwrap().id, 2);
+    assert_eq!(page.next_cursor, None);
+
+    let page = client.list_utilities_by_partner(&partner, &0, &1);
+    assert_eq!(page.utilities.get(0).unwrap().id, 0);
+    assert_eq!(page.next_cursor, Some(1));
+    let page = client.list_utilities_by_partner(&partner, &1, &1);
+    assert_eq!(page.utilities.get(0).unwrap().id, 3);
+    assert_eq!(page.next_cursor, None);
+
+    let page = client.list_utilities_by_provider(&Address::generate(&env), &0, &10);
+    assert_eq!(page.utilities.len(), 0);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn test_list_active_utilities() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider = Address::generate(&env);
+    for _ in 0..3 {
+        create_utility(&env, &client, &provider, &provider);
+    }
+    client.set_utility_paused(&1, &provider, &true);
+    client.update_utility(&2, &provider, &String::from_str(&env, "uri"), &5000);
+
+    let page = client.list_active_utilities(&0, &0, &10);
+    assert_eq!(page.utilities.len(), 2);
+    assert_eq!(page.utilities.get(0).unwrap().id, 0);
+    assert_eq!(page.utilities.get(1).unwrap().id, 2);
+
+    let page = client.list_active_utilities(&3000, &0, &10);
+    assert_eq!(page.utilities.len(), 1);
+    assert_eq!(page.utilities.get(0).unwrap().id, 2);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn test_update_utility() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider = Address::generate(&env);
+    create_utility(&env, &client, &provider, &provider);
+
+    let uri = String::from_str(&env, "https://example.com/v2");
+    let updated = client.update_utility(&0, &provider, &uri, &3000);
+
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (
+                    symbol_short!("utility"),
+                    symbol_short!("updated"),
+                    EVENT_VERSION,
+                    0_u64
+                )
+                    .into_val(&env),
+                UtilityUpdated {
+                    utility_uri: uri.clone(),
+                    offer_expiry: 3000,
+                }
+                .into_val(&env),
+            )
+        ]
+    );
+    assert_eq!(client.get_utility(&0), updated);
+    assert_eq!(updated.utility_uri, uri);
+}
+
+#[test]
+fn test_pause_utility() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let provider = Address::generate(&env);
+    create_utility(&env, &client, &provider, &provider);
+
+    client.set_utility_paused(&0, &provider, &true);
+
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (
+                    symbol_short!("utility"),
+                    symbol_short!("paused"),
+                    EVENT_VERSION,
+                    0_u64
+                )
+                    .into_val(&env),
+                UtilityPaused {
+                    paused: true,
+                    by: provider.clone(),
+                }
+                .into_val(&env),
+            )
+        ]
+    );
+    assert!(client.get_utility(&0).paused);
+
+    let result = client.try_join_raffle(&0, &Address::generate(&env));
+    assert_eq!(result, Err(Ok(UtilityError::UtilityPaused.into())));
+}
+
+#[test]
+fn test_mark_eligible_emits_event() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    client.setup_config(&admin);
+
+    client.mark_eligible(&3, &user, &admin);
+
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (
+                    symbol_short!("eligible"),
+                    symbol_short!("marked"),
+                    EVENT_VERSION,
+                    3_u64
+                )
+                    .into_val(&env),
+                EligibleMarked { user }.into_val(&env),
+            )
+        ]
+    );
 }
 
 // #[test]
@@ -311,3 +721,2922 @@ pub fn test_create_utility() {
 //     let ended_utility = client.get_utility(&0);
 //     assert!(ended_utility.raffle.ended);
 // }
+
+#[test]
+fn test_get_user_benefits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    client.setup_config(&admin);
+
+    let held = create_nft(&env, &user);
+    let not_held = create_nft(&env, &Address::generate(&env));
+    let unbound = create_nft(&env, &user);
+
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+    client.claim_utitlity_on_nft(&held, &0, &user, &admin);
+    client.claim_utitlity_on_nft(&held, &1, &user, &admin);
+    client.claim_utitlity_on_nft(&not_held, &0, &user, &admin);
+    client.redeem_utility_on_nft(&held, &0, &user);
+    // Eligibility reflects the rule join and claim enforce.
+    client.set_eligibility_rule(
+        &0,
+        &admin,
+        &Some(EligibilityRule::MinTokenBalance(held.clone(), 5)),
+    );
+
+    let benefits = client.get_user_benefits(&user, &vec![&env, held.clone(), not_held, unbound]);
+    assert_eq!(benefits.len(), 2);
+
+    let first = benefits.get(0).unwrap();
+    assert_eq!(first.token, held);
+    assert_eq!(first.utility_id, 0);
+    assert_eq!(first.usage_type, UsageType::Limited);
+    assert_eq!(first.usage_left, 9);
+    assert_eq!(first.expiry, 1000);
+    assert_eq!(first.raffle_status, RaffleStatus::Upcoming);
+    assert!(!first.reward_claimed);
+    assert!(!first.eligible);
+
+    let second = benefits.get(1).unwrap();
+    assert_eq!(second.utility_id, 1);
+    assert_eq!(second.usage_left, 10);
+    assert!(second.eligible);
+}
+
+#[test]
+fn test_utility_status() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    client.setup_config(&admin);
+    let nft = create_nft(&env, &user);
+    create_utility(&env, &client, &admin, &admin);
+
+    let status = client.utility_status(&nft, &0);
+    assert_eq!(status.reason, StatusReason::NotClaimed);
+    assert!(!client.check_utility(&nft, &0));
+
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(
+        client.utility_status(&nft, &0),
+        UtilityStatusReport {
+            utility_id: 0,
+            token: nft.clone(),
+            remaining_usages: Some(9),
+            expires_at: Some(1000),
+            redeemable: true,
+            reason: StatusReason::Redeemable,
+        }
+    );
+    assert!(client.check_utility(&nft, &0));
+
+    client.set_utility_paused(&0, &admin, &true);
+    assert_eq!(client.utility_status(&nft, &0).reason, StatusReason::Paused);
+    client.set_utility_paused(&0, &admin, &false);
+
+    env.ledger().with_mut(|l| l.timestamp = 1001);
+    let status = client.utility_status(&nft, &0);
+    assert_eq!(status.reason, StatusReason::Expired);
+    assert!(!status.redeemable);
+    assert_eq!(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        Err(Ok(UtilityError::UtilityExpired.into()))
+    );
+}
+
+#[test]
+fn test_utility_status_usage_exhausted() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    client.setup_config(&admin);
+    let nft = create_nft(&env, &user);
+
+    // Limited to one use and never expiring.
+    client.create_new_utility(
+        &vec![&env, admin.to_string(), admin.to_string()],
+        &String::from_str(&env, "https://example.com"),
+        &vec![&env, 0, 0, 2000],
+        &vec![&env, 1, 1],
+        &0,
+        &0,
+        &0,
+        &vec![&env],
+        &vec![&env, 1000, 100, 10],
+    );
+    // Unlimited and never expiring.
+    client.create_new_utility(
+        &vec![&env, admin.to_string(), admin.to_string()],
+        &String::from_str(&env, "https://example.com"),
+        &vec![&env, 0, 0, 2000],
+        &vec![&env, 0, 0],
+        &0,
+        &0,
+        &0,
+        &vec![&env],
+        &vec![&env, 1000, 100, 10],
+    );
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.claim_utitlity_on_nft(&nft, &1, &user, &admin);
+
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    let status = client.utility_status(&nft, &0);
+    assert_eq!(status.remaining_usages, Some(0));
+    assert_eq!(status.expires_at, None);
+    assert_eq!(status.reason, StatusReason::UsageExhausted);
+    assert_eq!(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        Err(Ok(UtilityError::UsageExceeded.into()))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 1_000_000);
+    client.redeem_utility_on_nft(&nft, &1, &user);
+    let status = client.utility_status(&nft, &1);
+    assert_eq!(status.remaining_usages, None);
+    assert_eq!(status.expires_at, None);
+    assert!(status.redeemable);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn try_create(
+    env: &Env,
+    client: &UtilityContractClient,
+    provider: &Address,
+    expiries: Vec<u64>,
+    usages: Vec<u64>,
+    raffle_start_time: u64,
+    selection: u32,
+    receipt: u32,
+    reward_tokens: Vec<Address>,
+    reward_amounts: Vec<i128>,
+) -> Result<
+    Result<Utility, soroban_sdk::ConversionError>,
+    Result<soroban_sdk::Error, soroban_sdk::InvokeError>,
+> {
+    client.try_create_new_utility(
+        &vec![
+            env,
+            provider.to_string(),
+            provider.to_string(),
+            provider.to_string(),
+        ],
+        &String::from_str(env, "https://example.com"),
+        &expiries,
+        &usages,
+        &raffle_start_time,
+        &selection,
+        &receipt,
+        &reward_tokens,
+        &reward_amounts,
+    )
+}
+
+#[test]
+fn test_create_invalid_raffle_time() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    env.ledger().with_mut(|l| l.timestamp = 100);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        50,
+        1,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InvalidTime);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        2500,
+        1,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InvalidTime);
+}
+
+#[test]
+fn test_create_insufficient_balance() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        2,
+        vec![&env, reward_token.clone()],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InsufficientBalance);
+
+    // The provider must hold the whole pool, not just some of it.
+    token::StellarAssetClient::new(&env, &reward_token).mint(&admin, &999);
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        2,
+        vec![&env, reward_token],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InsufficientBalance);
+}
+
+#[test]
+fn test_create_reward_amounts() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let create = |amounts: Vec<i128>| {
+        try_create(
+            &env,
+            &client,
+            &admin,
+            vec![&env, 1, 1000, 2000],
+            vec![&env, 1, 10],
+            500,
+            0,
+            0,
+            vec![&env],
+            amounts,
+        )
+    };
+
+    // Ten winners of 100 do not fit in a pool of 999.
+    assert_error(
+        create(vec![&env, 999, 100, 10]),
+        RewardError::InvalidRewardAmount,
+    );
+    assert_error(
+        create(vec![&env, -1, 0, 0]),
+        RewardError::InvalidRewardAmount,
+    );
+    assert_error(
+        create(vec![&env, 1000, 100, -1]),
+        RewardError::InvalidRewardAmount,
+    );
+    assert_error(
+        create(vec![&env, i128::MAX, i128::MAX, 2]),
+        UtilityError::ArithmeticOverflow,
+    );
+    assert_eq!(
+        create(vec![&env, 1000, 100, 10])
+            .unwrap()
+            .unwrap()
+            .reward
+            .decimals,
+        0
+    );
+
+    let reward_token = create_minted_reward(&env, &client, &admin);
+    let reward = client.get_utility(&1).reward;
+    assert_eq!(
+        reward.decimals,
+        token::TokenClient::new(&env, &reward_token).decimals()
+    );
+    assert_eq!(reward.total_amount, 1000);
+}
+
+#[test]
+fn test_create_invalid_expiry() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    env.ledger().with_mut(|l| l.timestamp = 100);
+
+    for expiries in [
+        vec![&env, 3, 1000, 2000],
+        vec![&env, 1, 1000, 50],
+        vec![&env, 2, 50, 2000],
+    ] {
+        let result = try_create(
+            &env,
+            &client,
+            &admin,
+            expiries,
+            vec![&env, 1, 10],
+            500,
+            0,
+            0,
+            vec![&env],
+            vec![&env, 1000, 100, 10],
+        );
+        assert_error(result, UtilityError::InvalidExpiry);
+    }
+}
+
+#[test]
+fn test_create_invalid_selection() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        2,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InvalidSelection);
+}
+
+#[test]
+fn test_create_invalid_receipt() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        5,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::InvalidReceipt);
+}
+
+#[test]
+fn test_create_invalid_usage() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    for usages in [vec![&env, 2, 10], vec![&env, 1, 0]] {
+        let result = try_create(
+            &env,
+            &client,
+            &admin,
+            vec![&env, 1, 1000, 2000],
+            usages,
+            500,
+            0,
+            0,
+            vec![&env],
+            vec![&env, 1000, 100, 10],
+        );
+        assert_error(result, UtilityError::InvalidUsage);
+    }
+}
+
+#[test]
+fn test_create_missing_parameter() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    );
+    assert_error(result, UtilityError::MissingParameter);
+
+    let result = try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100],
+    );
+    assert_error(result, UtilityError::MissingParameter);
+}
+
+#[test]
+fn test_not_initialized() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, UtilityContract);
+    let client = UtilityContractClient::new(&env, &contract_id);
+    let user = Address::generate(&env);
+
+    assert_error(
+        client.try_mark_eligible(&0, &user, &user),
+        UtilityError::NotInitialized,
+    );
+    assert_error(
+        client.try_claim_reward(&0, &user, &user),
+        UtilityError::NotInitialized,
+    );
+}
+
+#[test]
+fn test_not_authorized() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let provider = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    let nft = create_nft(&env, &admin);
+    create_utility(&env, &client, &provider, &provider);
+
+    assert_error(
+        client.try_update_utility(&0, &stranger, &String::from_str(&env, "uri"), &3000),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_set_utility_paused(&0, &stranger, &true),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_mark_eligible(&0, &stranger, &stranger),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_claim_reward(&0, &stranger, &stranger),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_claim_utitlity_on_nft(&nft, &0, &stranger, &stranger),
+        UtilityError::NotAuthorized,
+    );
+    // The admin is not the provider of this utility.
+    assert_error(
+        client.try_claim_utitlity_on_nft(&nft, &0, &admin, &admin),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_end_raffle(&0, &stranger),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &stranger),
+        UtilityError::NotAuthorized,
+    );
+}
+
+#[test]
+fn test_raffle_errors() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    create_utility(&env, &client, &admin, &admin);
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        0,
+        vec![&env],
+        vec![&env, 1000, 100, 10],
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_error(
+        client.try_join_raffle(&1, &user),
+        UtilityError::InvalidRaffleSelection,
+    );
+    assert_error(
+        client.try_end_raffle(&1, &admin),
+        UtilityError::InvalidRaffleSelection,
+    );
+    assert_error(
+        client.try_end_raffle(&0, &admin),
+        UtilityError::RaffleNotEnded,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    assert_error(
+        client.try_join_raffle(&0, &user),
+        UtilityError::RaffleExpired,
+    );
+    // Only the provider's signature ends the raffle.
+    env.set_auths(&[]);
+    assert!(client.try_end_raffle(&0, &admin).is_err());
+    env.mock_all_auths();
+    client.end_raffle(&0, &admin);
+    assert_error(
+        client.try_end_raffle(&0, &admin),
+        UtilityError::RaffleAlreadyEnded,
+    );
+}
+
+#[test]
+fn test_claim_reward_errors() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    let user = Address::generate(&env);
+
+    // One prize of 100 minted on claim.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env, reward_token.clone()],
+        vec![&env, 100, 100, 1],
+    )
+    .unwrap()
+    .unwrap();
+    // No receipt configured.
+    create_utility(&env, &client, &admin, &admin);
+    // Minted reward without a token address.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env],
+        vec![&env, 100, 100, 1],
+    )
+    .unwrap()
+    .unwrap();
+
+    client.claim_reward(&0, &user, &admin);
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&user),
+        100
+    );
+    assert_error(
+        client.try_claim_reward(&0, &Address::generate(&env), &admin),
+        UtilityError::AllRewardsClaimed,
+    );
+
+    assert_error(
+        client.try_claim_reward(&1, &user, &admin),
+        UtilityError::RaffleNotEnded,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.join_raffle(&1, &user);
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&1, &admin);
+    assert_error(
+        client.try_claim_reward(&1, &Address::generate(&env), &admin),
+        UtilityError::NotWinner,
+    );
+    assert_error(
+        client.try_claim_reward(&1, &user, &admin),
+        UtilityError::InvalidReceiptType,
+    );
+
+    assert_error(
+        client.try_claim_reward(&2, &user, &admin),
+        UtilityError::MissingParameter,
+    );
+}
+
+#[test]
+fn test_already_claimed() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    let user = Address::generate(&env);
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env, reward_token],
+        vec![&env, 1000, 100, 10],
+    )
+    .unwrap()
+    .unwrap();
+
+    client.claim_reward(&0, &user, &admin);
+    assert_error(
+        client.try_claim_reward(&0, &user, &admin),
+        UtilityError::AlreadyClaimed,
+    );
+}
+
+#[test]
+fn test_utility_not_found_and_not_claimed() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let nft = create_nft(&env, &admin);
+
+    assert_error(client.try_get_utility(&0), UtilityError::UtilityNotFound);
+    assert_error(
+        client.try_utility_status(&nft, &0),
+        UtilityError::UtilityNotFound,
+    );
+
+    create_utility(&env, &client, &admin, &admin);
+    assert_error(
+        client.try_get_token_utility(&nft, &0),
+        UtilityError::UtilityNotClaimed,
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &admin),
+        UtilityError::UtilityNotClaimed,
+    );
+}
+
+#[test]
+fn test_delegate_utility() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let owner = Address::generate(&env);
+    let renter = Address::generate(&env);
+    let nft = create_nft(&env, &owner);
+    create_utility(&env, &client, &admin, &admin);
+    client.claim_utitlity_on_nft(&nft, &0, &owner, &admin);
+
+    let d = client.delegate_utility(&nft, &0, &owner, &renter, &500, &2);
+    assert_eq!(client.get_delegation(&nft, &0), Some(d.clone()));
+    assert_error(
+        client.try_delegate_utility(&nft, &0, &owner, &Address::generate(&env), &500, &2),
+        UtilityError::DelegationActive,
+    );
+
+    // The renter redeems without holding the NFT; the owner is locked out.
+    client.redeem_utility_on_nft(&nft, &0, &renter);
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &owner),
+        UtilityError::DelegationActive,
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &Address::generate(&env)),
+        UtilityError::NotAuthorized,
+    );
+    client.redeem_utility_on_nft(&nft, &0, &renter);
+    assert_eq!(client.get_delegation(&nft, &0).unwrap().uses, 2);
+    assert_eq!(client.utility_status(&nft, &0).remaining_usages, Some(8));
+
+    // Once the uses are spent the delegation lapses and the owner is back.
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &renter),
+        UtilityError::NotAuthorized,
+    );
+    client.redeem_utility_on_nft(&nft, &0, &owner);
+}
+
+#[test]
+fn test_delegation_expiry_and_revoke() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let owner = Address::generate(&env);
+    let renter = Address::generate(&env);
+    let nft = create_nft(&env, &owner);
+    create_utility(&env, &client, &admin, &admin);
+    client.claim_utitlity_on_nft(&nft, &0, &owner, &admin);
+
+    client.delegate_utility(&nft, &0, &owner, &renter, &100, &5);
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &renter),
+        UtilityError::NotAuthorized,
+    );
+    client.redeem_utility_on_nft(&nft, &0, &owner);
+
+    client.delegate_utility(&nft, &0, &owner, &renter, &500, &5);
+    assert_error(
+        client.try_revoke_delegation(&nft, &0, &renter),
+        UtilityError::NotAuthorized,
+    );
+    client.revoke_delegation(&nft, &0, &owner);
+    assert_eq!(client.get_delegation(&nft, &0), None);
+    assert_error(
+        client.try_revoke_delegation(&nft, &0, &owner),
+        UtilityError::DelegationNotFound,
+    );
+    client.redeem_utility_on_nft(&nft, &0, &owner);
+}
+
+#[test]
+fn test_delegate_utility_errors() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let owner = Address::generate(&env);
+    let renter = Address::generate(&env);
+    let nft = create_nft(&env, &owner);
+    create_utility(&env, &client, &admin, &admin);
+    env.ledger().with_mut(|l| l.timestamp = 10);
+
+    assert_error(
+        client.try_delegate_utility(&nft, &0, &renter, &owner, &500, &1),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_delegate_utility(&nft, &0, &owner, &owner, &500, &1),
+        UtilityError::InvalidDelegation,
+    );
+    assert_error(
+        client.try_delegate_utility(&nft, &0, &owner, &renter, &500, &0),
+        UtilityError::InvalidDelegation,
+    );
+    assert_error(
+        client.try_delegate_utility(&nft, &0, &owner, &renter, &10, &1),
+        UtilityError::InvalidDelegation,
+    );
+    assert_error(
+        client.try_delegate_utility(&nft, &1, &owner, &renter, &500, &1),
+        UtilityError::UtilityNotFound,
+    );
+}
+
+fn transfer_nft(env: &Env, nft: &Address, from: &Address, to: &Address) {
+    token::TokenClient::new(env, nft).transfer(from, to, &1);
+}
+
+#[test]
+fn test_transfer_policy_on_redeem() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let nft = create_nft(&env, &seller);
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+    client.set_transfer_policy(&0, &admin, &TransferPolicy::ResetOnTransfer);
+    client.set_transfer_policy(&2, &admin, &TransferPolicy::Soulbound);
+    for id in 0..3 {
+        client.claim_utitlity_on_nft(&nft, &id, &seller, &admin);
+        client.redeem_utility_on_nft(&nft, &id, &seller);
+        client.redeem_utility_on_nft(&nft, &id, &seller);
+    }
+
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    transfer_nft(&env, &nft, &seller, &buyer);
+
+    // Reset: the buyer starts from a fresh grant.
+    client.redeem_utility_on_nft(&nft, &0, &buyer);
+    let state = client.get_token_utility(&nft, &0);
+    assert_eq!(state.owner, buyer);
+    assert_eq!(state.usage, 9);
+    assert_eq!(state.expiry, 1100);
+    assert_eq!(state.claimed_at, 100);
+
+    // Carry over: the buyer inherits what the seller left.
+    client.redeem_utility_on_nft(&nft, &1, &buyer);
+    let state = client.get_token_utility(&nft, &1);
+    assert_eq!(state.owner, buyer);
+    assert_eq!(state.usage, 7);
+    assert_eq!(state.expiry, 1000);
+
+    // Soulbound: nobody can redeem once the NFT has moved on.
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &2, &buyer),
+        UtilityError::SoulboundUtility,
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &2, &seller),
+        UtilityError::NotAuthorized,
+    );
+}
+
+#[test]
+fn test_on_nft_transfer_hook() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let nft = create_nft(&env, &seller);
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+    client.set_transfer_policy(&0, &admin, &TransferPolicy::ResetOnTransfer);
+    client.set_transfer_policy(&1, &admin, &TransferPolicy::Soulbound);
+    client.claim_utitlity_on_nft(&nft, &0, &seller, &admin);
+    client.claim_utitlity_on_nft(&nft, &1, &seller, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &seller);
+
+    transfer_nft(&env, &nft, &seller, &buyer);
+    client.on_nft_transfer(&nft, &seller, &buyer);
+
+    assert_eq!(env.auths().last().unwrap().0, nft,);
+    let state = client.get_token_utility(&nft, &0);
+    assert_eq!(state.owner, buyer);
+    assert_eq!(state.usage, 10);
+    assert_eq!(client.get_token_utility(&nft, &1).owner, seller);
+}
+
+#[test]
+fn test_register_holder() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let holder = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let nft = create_nft(&env, &holder);
+
+    assert_error(
+        client.try_register_holder(&nft, &buyer),
+        UtilityError::NotAuthorized,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 10);
+    assert_eq!(client.register_holder(&nft, &holder), 10);
+    env.ledger().with_mut(|l| l.timestamp = 20);
+    assert_eq!(client.register_holder(&nft, &holder), 10);
+    assert_eq!(client.get_holder_since(&nft, &holder), Some(10));
+
+    // The transfer hook drops the seller's record.
+    transfer_nft(&env, &nft, &holder, &buyer);
+    client.on_nft_transfer(&nft, &holder, &buyer);
+    assert_eq!(client.get_holder_since(&nft, &holder), None);
+    assert_eq!(client.register_holder(&nft, &buyer), 20);
+}
+
+#[test]
+fn test_holder_balance_drop() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let holder = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let nft = create_nft(&env, &holder);
+    token::StellarAssetClient::new(&env, &nft).mint(&holder, &1);
+    create_utility(&env, &client, &admin, &admin);
+    client.set_eligibility_rule(
+        &0,
+        &admin,
+        &Some(EligibilityRule::HeldSince(nft.clone(), 100)),
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 10);
+    client.register_holder(&nft, &holder);
+    env.ledger().with_mut(|l| l.timestamp = 200);
+    assert!(client.evaluate_eligibility(&0, &holder));
+
+    // Selling without the transfer hook still voids the registration.
+    transfer_nft(&env, &nft, &holder, &buyer);
+    assert!(!client.evaluate_eligibility(&0, &holder));
+    assert_eq!(client.register_holder(&nft, &holder), 200);
+    assert!(!client.evaluate_eligibility(&0, &holder));
+    env.ledger().with_mut(|l| l.timestamp = 300);
+    assert!(client.evaluate_eligibility(&0, &holder));
+}
+
+#[test]
+fn test_holding_period_on_redeem() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let holder = Address::generate(&env);
+    let nft = create_nft(&env, &holder);
+    create_utility(&env, &client, &admin, &admin);
+    client.claim_utitlity_on_nft(&nft, &0, &holder, &admin);
+    client.set_holding_requirement(&0, &admin, &vec![&env, nft.clone()], &100);
+
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &holder),
+        UtilityError::HoldingPeriodNotMet,
+    );
+    client.register_holder(&nft, &holder);
+    env.ledger().with_mut(|l| l.timestamp = 99);
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &holder),
+        UtilityError::HoldingPeriodNotMet,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    client.redeem_utility_on_nft(&nft, &0, &holder);
+}
+
+#[test]
+fn test_holding_period_on_join_and_claim() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    let other = create_nft(&env, &Address::generate(&env));
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    create_utility(&env, &client, &admin, &admin);
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env, reward_token],
+        vec![&env, 1000, 100, 10],
+    )
+    .unwrap()
+    .unwrap();
+    for id in 0..2 {
+        client.set_holding_requirement(&id, &admin, &vec![&env, other.clone(), nft.clone()], &100);
+    }
+    client.set_raffle_window(&0, &admin, &0, &2000, &0);
+    client.register_holder(&nft, &user);
+
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_error(
+        client.try_join_raffle(&0, &user),
+        UtilityError::HoldingPeriodNotMet,
+    );
+    assert_error(
+        client.try_claim_reward(&1, &user, &admin),
+        UtilityError::HoldingPeriodNotMet,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 150);
+    client.join_raffle(&0, &user);
+    client.claim_reward(&1, &user, &admin);
+}
+
+#[test]
+fn test_set_holding_requirement_errors() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let nft = create_nft(&env, &admin);
+    create_utility(&env, &client, &admin, &admin);
+
+    assert_error(
+        client.try_set_holding_requirement(&0, &Address::generate(&env), &vec![&env], &100),
+        UtilityError::NotAuthorized,
+    );
+    client.set_config(&admin, &ConfigUpdate::MaxBatchSize(1));
+    assert_error(
+        client.try_set_holding_requirement(&0, &admin, &vec![&env, nft.clone(), nft], &100),
+        UtilityError::BatchTooLarge,
+    );
+    assert_error(
+        client.try_set_holding_requirement(&0, &admin, &vec![&env], &100),
+        UtilityError::InvalidConfig,
+    );
+    client.set_holding_requirement(&0, &admin, &vec![&env], &0);
+}
+
+#[test]
+fn test_eligibility_rules() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let banned = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    create_nft(&env, &banned);
+    let points = create_nft(&env, &banned);
+    create_utility(&env, &client, &admin, &admin);
+
+    assert!(client.evaluate_eligibility(&0, &user));
+
+    // Holders of the NFT or anyone with 1 point, unless on the denylist.
+    let rule = EligibilityRule::AllOf(vec![
+        &env,
+        EligibilityRule::AnyOf(vec![
+            &env,
+            EligibilityRule::OwnsCollection(vec![&env, nft.clone()]),
+            EligibilityRule::MinTokenBalance(points, 1),
+        ]),
+        EligibilityRule::Not(vec![
+            &env,
+            EligibilityRule::OnWhitelist(symbol_short!("deny")),
+        ]),
+    ]);
+    client.set_eligibility_rule(&0, &admin, &Some(rule.clone()));
+    assert_eq!(client.get_eligibility_rule(&0), Some(rule));
+
+    assert!(client.evaluate_eligibility(&0, &user));
+    assert!(client.evaluate_eligibility(&0, &banned));
+    assert!(!client.evaluate_eligibility(&0, &Address::generate(&env)));
+
+    client.set_list_members(
+        &0,
+        &admin,
+        &symbol_short!("deny"),
+        &vec![&env, banned.clone()],
+        &true,
+    );
+    assert!(client.is_list_member(&0, &symbol_short!("deny"), &banned));
+    assert!(!client.evaluate_eligibility(&0, &banned));
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    assert_error(
+        client.try_join_raffle(&0, &banned),
+        UtilityError::NotEligible,
+    );
+    client.join_raffle(&0, &user);
+
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+
+    client.set_eligibility_rule(
+        &0,
+        &admin,
+        &Some(EligibilityRule::HeldSince(nft.clone(), 100)),
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        UtilityError::NotEligible,
+    );
+
+    // Admin-marked users, through the `MarkedEligible` rule.
+    client.set_eligibility_rule(&0, &admin, &Some(EligibilityRule::MarkedEligible));
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        UtilityError::NotEligible,
+    );
+    client.mark_eligible(&0, &user, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+
+    client.set_eligibility_rule(&0, &admin, &None);
+    assert_eq!(client.get_eligibility_rule(&0), None);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+}
+
+#[test]
+fn test_eligibility_rule_limits() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    create_utility(&env, &client, &admin, &admin);
+
+    assert_error(
+        client.try_set_eligibility_rule(
+            &0,
+            &Address::generate(&env),
+            &Some(EligibilityRule::OnWhitelist(symbol_short!("vip"))),
+        ),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_set_eligibility_rule(&0, &admin, &Some(EligibilityRule::AnyOf(vec![&env]))),
+        UtilityError::InvalidRule,
+    );
+    assert_error(
+        client.try_set_eligibility_rule(
+            &0,
+            &admin,
+            &Some(EligibilityRule::Not(vec![
+                &env,
+                EligibilityRule::OnWhitelist(symbol_short!("a")),
+                EligibilityRule::OnWhitelist(symbol_short!("b")),
+            ])),
+        ),
+        UtilityError::InvalidRule,
+    );
+
+    let mut deep = EligibilityRule::OnWhitelist(symbol_short!("vip"));
+    for _ in 0..=eligibility::MAX_RULE_DEPTH {
+        deep = EligibilityRule::Not(vec![&env, deep]);
+    }
+    assert_error(
+        client.try_set_eligibility_rule(&0, &admin, &Some(deep)),
+        UtilityError::InvalidRule,
+    );
+
+    let mut wide = vec![&env];
+    for _ in 0..eligibility::MAX_RULE_NODES {
+        wide.push_back(EligibilityRule::OnWhitelist(symbol_short!("vip")));
+    }
+    assert_error(
+        client.try_set_eligibility_rule(&0, &admin, &Some(EligibilityRule::AnyOf(wide))),
+        UtilityError::InvalidRule,
+    );
+}
+
+#[test]
+fn test_weighted_raffle_draw() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let heavy = Address::generate(&env);
+    let light = Address::generate(&env);
+    let collection = create_nft(&env, &heavy);
+    let sac = token::StellarAssetClient::new(&env, &collection);
+    sac.mint(&heavy, &2);
+    sac.mint(&light, &1);
+
+    // A single winner.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        1,
+        0,
+        vec![&env],
+        vec![&env, 100, 100, 1],
+    )
+    .unwrap()
+    .unwrap();
+    client.set_entry_weight(&0, &admin, &EntryWeight::NftCount(collection.clone()));
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    assert_error(
+        client.try_join_raffle(&0, &Address::generate(&env)),
+        UtilityError::NoEntryWeight,
+    );
+    client.join_raffle(&0, &heavy);
+    client.join_raffle(&0, &light);
+    assert_error(
+        client.try_join_raffle(&0, &light),
+        UtilityError::AlreadyJoined,
+    );
+    assert_error(
+        client.try_set_entry_weight(&0, &admin, &EntryWeight::Equal),
+        UtilityError::RaffleHasEntrants,
+    );
+
+    // Weights are captured at join time.
+    sac.mint(&light, &100);
+    assert_eq!(client.get_entrant_count(&0), 2);
+    assert_eq!(
+        client.list_entrants(&0, &0, &10),
+        vec![
+            &env,
+            RaffleEntry {
+                user: heavy.clone(),
+                weight: 3
+            },
+            RaffleEntry {
+                user: light.clone(),
+                weight: 1
+            },
+        ]
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+
+    // Anyone can replay the draw from the published seed.
+    let seed = client.get_raffle_seed(&0).unwrap();
+    let mut input = Bytes::from_array(&env, &seed.to_array());
+    input.extend_from_array(&0u32.to_be_bytes());
+    let digest = env.crypto().sha256(&input).to_array();
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    let expected = if u64::from_be_bytes(head) % 4 < 3 {
+        heavy.clone()
+    } else {
+        light.clone()
+    };
+
+    assert_eq!(client.get_raffle_winners(&0), vec![&env, expected.clone()]);
+    assert!(client.is_raffle_winner(&0, &expected));
+    let loser = if expected == heavy { light } else { heavy };
+    assert!(!client.is_raffle_winner(&0, &loser));
+}
+
+#[test]
+fn test_huge_entry_weights() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let whales = [Address::generate(&env), Address::generate(&env)];
+    let weight_token = create_payment_token(&env, &[]);
+    let sac = token::StellarAssetClient::new(&env, &weight_token);
+    create_utility(&env, &client, &admin, &admin);
+    client.set_entry_weight(&0, &admin, &EntryWeight::TokenBalance(weight_token, 1));
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    for whale in whales.iter() {
+        sac.mint(whale, &10_000_000_000_000_000_000);
+        client.join_raffle(&0, whale);
+    }
+    assert_eq!(
+        client.list_entrants(&0, &0, &1).get_unchecked(0).weight,
+        raffle::MAX_ENTRY_WEIGHT
+    );
+
+    // Weights this large still add up, so the raffle can end.
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    assert_eq!(client.get_raffle_winners(&0).len(), 2);
+}
+
+#[test]
+fn test_raffle_entrant_cap() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    create_utility(&env, &client, &admin, &admin);
+    assert_error(
+        client.try_set_raffle_window(&0, &admin, &500, &2000, &(raffle::MAX_ENTRANTS + 1)),
+        UtilityError::InvalidConfig,
+    );
+    client.set_claim_deadline(&0, &admin, &3000);
+
+    // Without a configured cap the raffle fills up at MAX_ENTRANTS.
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    env.budget().reset_unlimited();
+    for _ in 0..raffle::MAX_ENTRANTS {
+        client.join_raffle(&0, &Address::generate(&env));
+    }
+    env.budget().reset_default();
+    assert_error(
+        client.try_join_raffle(&0, &Address::generate(&env)),
+        UtilityError::RaffleFull,
+    );
+
+    // A full raffle still draws within a single transaction's budget.
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    env.budget().reset_default();
+    client.end_raffle(&0, &admin);
+    let winners = client.get_raffle_winners(&0);
+    assert_eq!(winners.len(), 10);
+    assert_eq!(
+        client
+            .list_entrants(&0, &(raffle::MAX_ENTRANTS - 1), &10)
+            .len(),
+        1
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 3001);
+    env.budget().reset_default();
+    let rerolled = client.reroll_unclaimed(&0, &admin, &4000);
+    assert_eq!(rerolled.len(), 10);
+    assert!(!client.is_raffle_winner(&0, &winners.get_unchecked(0)));
+    assert!(client.is_raffle_winner(&0, &rerolled.get_unchecked(0)));
+}
+
+#[test]
+fn test_ticket_weighted_raffle() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let users = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    create_utility(&env, &client, &admin, &admin);
+    client.set_entry_weight(&0, &admin, &EntryWeight::Tickets);
+
+    assert_error(
+        client.try_grant_tickets(&0, &users[0], &users[0], &5),
+        UtilityError::NotAuthorized,
+    );
+    client.grant_tickets(&0, &admin, &users[0], &5);
+    client.grant_tickets(&0, &admin, &users[0], &2);
+    client.grant_tickets(&0, &admin, &users[1], &1);
+    assert_eq!(client.get_tickets(&0, &users[0]), 7);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    assert_error(
+        client.try_join_raffle(&0, &users[2]),
+        UtilityError::NoEntryWeight,
+    );
+    client.join_raffle(&0, &users[0]);
+    client.join_raffle(&0, &users[1]);
+    assert_error(
+        client.try_grant_tickets(&0, &admin, &users[0], &1),
+        UtilityError::AlreadyJoined,
+    );
+    assert_eq!(client.list_entrants(&0, &1, &10).len(), 1);
+
+    // Fewer entrants than prizes: everyone wins, in draw order.
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    let winners = client.get_raffle_winners(&0);
+    assert_eq!(winners.len(), 2);
+    assert!(winners.contains(&users[0]) && winners.contains(&users[1]));
+}
+
+fn create_payment_token(env: &Env, holders: &[&Address]) -> Address {
+    let token_address = env
+        .register_stellar_asset_contract_v2(Address::generate(env))
+        .address();
+    for holder in holders {
+        token::StellarAssetClient::new(env, &token_address).mint(holder, &1000);
+    }
+    token_address
+}
+
+#[test]
+fn test_paid_tickets() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let provider = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let payment = create_payment_token(&env, &[&alice, &bob]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &payment).balance(who);
+    create_utility(&env, &client, &provider, &provider);
+    client.set_config(&admin, &ConfigUpdate::PlatformFeeBps(1000));
+
+    assert_error(
+        client.try_buy_tickets(&0, &alice, &1),
+        UtilityError::NoTicketSale,
+    );
+    assert_error(
+        client.try_set_ticket_sale(&0, &provider, &payment, &0, &3, &2),
+        UtilityError::InvalidConfig,
+    );
+    client.set_ticket_sale(&0, &provider, &payment, &10, &3, &2);
+    assert_eq!(client.get_utility(&0).raffle.weight, EntryWeight::Tickets);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    // Joining buys one ticket; more can be bought up to the cap.
+    client.join_raffle(&0, &alice);
+    client.buy_tickets(&0, &alice, &2);
+    assert_error(
+        client.try_buy_tickets(&0, &alice, &1),
+        UtilityError::TicketCapExceeded,
+    );
+    client.buy_tickets(&0, &bob, &1);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("raffle"),
+                    symbol_short!("joined"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RaffleJoined { user: bob.clone() }.into_val(&env),
+            ),
+        ]
+    );
+
+    assert_eq!(client.get_tickets(&0, &alice), 3);
+    assert_eq!(client.list_entrants(&0, &0, &10).get_unchecked(0).weight, 3);
+    assert_eq!(balance(&alice), 970);
+    assert_eq!(client.get_escrow_balance(&0), 40);
+
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &provider);
+    assert_eq!(client.get_escrow_balance(&0), 0);
+    assert_eq!(balance(&provider), 36);
+    assert_eq!(client.get_fee_balance(&payment), 4);
+    assert_error(
+        client.try_claim_refund(&0, &alice),
+        UtilityError::RefundUnavailable,
+    );
+}
+
+#[test]
+fn test_raffle_refunds() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let alice = Address::generate(&env);
+    let payment = create_payment_token(&env, &[&alice]);
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    for id in 0..2 {
+        client.set_ticket_sale(&id, &admin, &payment, &10, &5, &2);
+        client.buy_tickets(&id, &alice, &2);
+    }
+
+    // Too few entrants: the draw is called off.
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    let u = client.get_utility(&0);
+    assert!(u.raffle.ended && u.raffle.cancelled);
+    assert!(client.get_raffle_winners(&0).is_empty());
+    assert_eq!(client.get_refund_due(&0, &alice), 20);
+    assert_eq!(client.claim_refund(&0, &alice), 20);
+    assert_error(
+        client.try_claim_refund(&0, &alice),
+        UtilityError::NothingToRefund,
+    );
+
+    // Explicit cancellation.
+    assert_error(
+        client.try_cancel_raffle(&1, &alice),
+        UtilityError::NotAuthorized,
+    );
+    client.cancel_raffle(&1, &admin);
+    assert_error(
+        client.try_cancel_raffle(&1, &admin),
+        UtilityError::RaffleAlreadyEnded,
+    );
+    assert_error(
+        client.try_buy_tickets(&1, &alice, &1),
+        UtilityError::InvalidRaffleSelection,
+    );
+    client.claim_refund(&1, &alice);
+    assert_eq!(client.get_escrow_balance(&1), 0);
+    assert_eq!(
+        token::TokenClient::new(&env, &payment).balance(&alice),
+        1000
+    );
+}
+
+#[test]
+fn test_raffle_window() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let users = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    create_utility(&env, &client, &admin, &admin);
+
+    assert_error(
+        client.try_set_raffle_window(&0, &users[0], &500, &1000, &2),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_set_raffle_window(&0, &admin, &500, &2001, &2),
+        UtilityError::InvalidTime,
+    );
+    assert_error(
+        client.try_set_raffle_window(&0, &admin, &1001, &1000, &2),
+        UtilityError::InvalidTime,
+    );
+    client.set_raffle_window(&0, &admin, &500, &1000, &2);
+
+    env.ledger().with_mut(|l| l.timestamp = 499);
+    assert_error(
+        client.try_join_raffle(&0, &users[0]),
+        UtilityError::RaffleNotStarted,
+    );
+
+    // Entrants sign for themselves.
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.join_raffle(&0, &users[0]);
+    assert_eq!(env.auths()[0].0, users[0]);
+    client.join_raffle(&0, &users[1]);
+    assert_error(
+        client.try_join_raffle(&0, &users[2]),
+        UtilityError::RaffleFull,
+    );
+    assert_error(
+        client.try_set_raffle_window(&0, &admin, &500, &1000, &1),
+        UtilityError::InvalidConfig,
+    );
+
+    client.set_raffle_window(&0, &admin, &500, &1000, &0);
+    env.ledger().with_mut(|l| l.timestamp = 1001);
+    assert_error(
+        client.try_join_raffle(&0, &users[2]),
+        UtilityError::RaffleExpired,
+    );
+}
+
+#[test]
+fn test_reroll_unclaimed() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    let users = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    // One minted prize of 100.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        1,
+        1,
+        vec![&env, reward_token.clone()],
+        vec![&env, 100, 100, 1],
+    )
+    .unwrap()
+    .unwrap();
+    assert_error(
+        client.try_set_claim_deadline(&0, &admin, &2000),
+        UtilityError::InvalidTime,
+    );
+    client.set_claim_deadline(&0, &admin, &3000);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    for user in users.iter() {
+        client.join_raffle(&0, user);
+    }
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    let first = client.get_raffle_winners(&0).get_unchecked(0);
+
+    // Winners keep at least the window they were drawn with.
+    assert_error(
+        client.try_set_claim_deadline(&0, &admin, &2002),
+        UtilityError::InvalidTime,
+    );
+    client.set_claim_deadline(&0, &admin, &3000);
+
+    assert_error(
+        client.try_reroll_unclaimed(&0, &admin, &4000),
+        UtilityError::ClaimWindowOpen,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 3001);
+    assert_error(
+        client.try_claim_reward(&0, &first, &admin),
+        UtilityError::ClaimDeadlinePassed,
+    );
+    assert_error(
+        client.try_reroll_unclaimed(&0, &admin, &3001),
+        UtilityError::InvalidTime,
+    );
+
+    let rerolled = client.reroll_unclaimed(&0, &admin, &4000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("raffle"),
+                    symbol_short!("rerolled"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RaffleRerolled {
+                    lapsed: vec![&env, first.clone()],
+                    winners: rerolled.clone(),
+                    claim_deadline: 4000,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    let second = rerolled.get_unchecked(0);
+    assert_ne!(second, first);
+    assert!(!client.is_raffle_winner(&0, &first));
+    assert_eq!(client.get_raffle_winners(&0), vec![&env, second.clone()]);
+    assert_error(
+        client.try_claim_reward(&0, &first, &admin),
+        UtilityError::NotWinner,
+    );
+
+    client.claim_reward(&0, &second, &admin);
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&second),
+        100
+    );
+    env.ledger().with_mut(|l| l.timestamp = 4001);
+    assert_error(
+        client.try_reroll_unclaimed(&0, &admin, &5000),
+        UtilityError::NoUnclaimedPrizes,
+    );
+}
+
+#[test]
+fn test_sweep_unclaimed() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    let sac = token::StellarAssetClient::new(&env, &reward_token);
+    sac.mint(&admin, &300);
+    token::TokenClient::new(&env, &reward_token).approve(&admin, &admin, &300, &1000);
+    sac.mint(&client.address, &500);
+    // A pool of 300 escrowed from the provider, next to 500 held for other
+    // purposes.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        1,
+        2,
+        vec![&env, reward_token.clone()],
+        vec![&env, 300, 100, 2],
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(client.get_reward_escrow(&0), 300);
+    client.set_claim_deadline(&0, &admin, &3000);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.join_raffle(&0, &user);
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    assert_error(
+        client.try_sweep_unclaimed(&0, &admin),
+        UtilityError::ClaimWindowOpen,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 3001);
+    assert_error(
+        client.try_sweep_unclaimed(&0, &user),
+        UtilityError::NotAuthorized,
+    );
+    assert_eq!(client.sweep_unclaimed(&0, &admin), 300);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("raffle"),
+                    symbol_short!("swept"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                PrizesSwept {
+                    lapsed: 1,
+                    amount: 300,
+                    to: admin.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&admin),
+        300
+    );
+    // Funds the contract holds for anything else are left alone.
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&client.address),
+        500
+    );
+    assert_eq!(client.get_reward_escrow(&0), 0);
+    assert!(client.get_raffle_winners(&0).is_empty());
+    assert_error(
+        client.try_sweep_unclaimed(&0, &admin),
+        UtilityError::NoUnclaimedPrizes,
+    );
+}
+
+#[test]
+fn test_giveaway_first_come_first_served() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    let users = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    // Three minted prizes of 100, no raffle.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env, reward_token.clone()],
+        vec![&env, 300, 100, 3],
+    )
+    .unwrap()
+    .unwrap();
+    create_utility(&env, &client, &admin, &admin);
+
+    // Without the giveaway type only the admin can claim.
+    assert_error(
+        client.try_claim_reward(&0, &users[0], &users[0]),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_set_utility_type(&1, &admin, &UtilityType::Giveaway),
+        UtilityError::InvalidSelection,
+    );
+    client.set_utility_type(&0, &admin, &UtilityType::Giveaway);
+    assert_error(
+        client.try_set_claim_rate_limit(&0, &admin, &60, &0),
+        UtilityError::InvalidConfig,
+    );
+    client.set_claim_rate_limit(&0, &admin, &60, &2);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("giveaway"),
+                    symbol_short!("ratelimit"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RateLimitSet {
+                    window: 60,
+                    max_per_window: 2
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+
+    client.claim_reward(&0, &users[0], &users[0]);
+    assert_eq!(env.auths()[0].0, users[0]);
+    client.claim_reward(&0, &users[1], &users[1]);
+    assert_error(
+        client.try_claim_reward(&0, &users[2], &users[2]),
+        UtilityError::RateLimited,
+    );
+
+    // The next window admits the last prize.
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    client.claim_reward(&0, &users[2], &users[2]);
+    assert_eq!(client.get_giveaway_claims(&0), 3);
+    assert_error(
+        client.try_claim_reward(&0, &users[3], &users[3]),
+        UtilityError::AllRewardsClaimed,
+    );
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&users[2]),
+        100
+    );
+
+    client.set_claim_rate_limit(&0, &admin, &0, &0);
+    assert_eq!(client.get_claim_rate_limit(&0), None);
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    assert_error(
+        client.try_claim_reward(&0, &users[3], &users[3]),
+        UtilityError::UtilityExpired,
+    );
+}
+
+fn create_minted_reward(env: &Env, client: &UtilityContractClient, provider: &Address) -> Address {
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    try_create(
+        env,
+        client,
+        provider,
+        vec![env, 1, 1000, 2000],
+        vec![env, 1, 10],
+        500,
+        0,
+        1,
+        vec![env, reward_token.clone()],
+        vec![env, 1000, 100, 10],
+    )
+    .unwrap()
+    .unwrap();
+    reward_token
+}
+
+#[test]
+fn test_claim_requires_admin_auth() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let attacker = Address::generate(&env);
+    let reward_token = create_minted_reward(&env, &client, &admin);
+    let balance = || token::TokenClient::new(&env, &reward_token).balance(&attacker);
+
+    // Naming the admin as sender is not enough without its signature.
+    env.set_auths(&[]);
+    assert!(client.try_claim_reward(&0, &attacker, &admin).is_err());
+    assert!(client.try_mark_eligible(&0, &attacker, &admin).is_err());
+    assert_eq!(balance(), 0);
+    assert!(!client.is_eligible(&0, &attacker));
+
+    env.mock_all_auths();
+    client.claim_reward(&0, &attacker, &admin);
+    assert_eq!(balance(), 100);
+}
+
+#[test]
+fn test_linear_vesting() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let reward_token = create_minted_reward(&env, &client, &admin);
+    let balance = || token::TokenClient::new(&env, &reward_token).balance(&user);
+
+    assert_error(
+        client.try_set_vesting(&0, &admin, &Vesting::Linear(200, 100)),
+        RewardError::InvalidVesting,
+    );
+    client.set_vesting(&0, &admin, &Vesting::Linear(100, 1000));
+
+    client.claim_reward(&0, &user, &admin);
+    assert_eq!(balance(), 0);
+    assert_eq!(
+        client.get_vesting_grant(&0, &user),
+        Some(VestingGrant {
+            token: reward_token.clone(),
+            total: 100,
+            withdrawn: 0,
+            start: 0,
+            vesting: Vesting::Linear(100, 1000),
+        })
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 99);
+    assert_error(
+        client.try_withdraw_vested(&0, &user),
+        RewardError::NothingToWithdraw,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    assert_eq!(client.get_vested_amount(&0, &user), 50);
+    assert_eq!(client.withdraw_vested(&0, &user), 50);
+    assert_eq!(balance(), 50);
+    assert_eq!(client.get_withdrawn_amount(&0, &user), 50);
+    assert_error(
+        client.try_withdraw_vested(&0, &user),
+        RewardError::NothingToWithdraw,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 5000);
+    assert_eq!(client.withdraw_vested(&0, &user), 50);
+    assert_eq!(balance(), 100);
+    assert_error(
+        client.try_withdraw_vested(&0, &admin),
+        RewardError::NoVestingGrant,
+    );
+}
+
+#[test]
+fn test_cancel_and_clawback() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let linear = Address::generate(&env);
+    let cliff = Address::generate(&env);
+    let reward_token = create_minted_reward(&env, &client, &admin);
+    create_minted_reward(&env, &client, &admin);
+
+    client.set_vesting(&0, &admin, &Vesting::Linear(0, 1000));
+    client.claim_reward(&0, &linear, &admin);
+    client.set_vesting(&0, &admin, &Vesting::Cliff(500));
+    client.claim_reward(&0, &cliff, &admin);
+
+    assert_error(
+        client.try_clawback_unvested(&0, &admin, &vec![&env, linear.clone()]),
+        UtilityError::UtilityNotCancelled,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 250);
+    assert_error(
+        client.try_cancel_utility(&0, &linear),
+        UtilityError::NotAuthorized,
+    );
+    client.cancel_utility(&0, &admin);
+    assert_error(
+        client.try_cancel_utility(&0, &admin),
+        UtilityError::UtilityCancelled,
+    );
+    assert_error(
+        client.try_set_utility_paused(&0, &admin, &false),
+        UtilityError::UtilityCancelled,
+    );
+    assert!(client.get_utility(&0).paused);
+
+    // Vesting stops at cancellation.
+    env.ledger().with_mut(|l| l.timestamp = 2000);
+    assert_eq!(client.get_vested_amount(&0, &linear), 25);
+    assert_eq!(client.get_vested_amount(&0, &cliff), 0);
+
+    assert_eq!(
+        client.clawback_unvested(&0, &admin, &vec![&env, linear.clone(), cliff.clone()]),
+        175
+    );
+    assert_eq!(client.get_vesting_grant(&0, &linear).unwrap().total, 25);
+    assert_eq!(client.withdraw_vested(&0, &linear), 25);
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&linear),
+        25
+    );
+    assert_error(
+        client.try_withdraw_vested(&0, &cliff),
+        RewardError::NothingToWithdraw,
+    );
+    assert_eq!(client.clawback_unvested(&0, &admin, &vec![&env, linear]), 0);
+}
+
+fn create_points_token<'a>(
+    env: &Env,
+    client: &UtilityContractClient,
+    admin: &Address,
+) -> points_token::PointsTokenClient<'a> {
+    let points_id = env.register_contract(None, points_token::PointsToken);
+    let points = points_token::PointsTokenClient::new(env, &points_id);
+    points.initialize(
+        admin,
+        &0,
+        &String::from_str(env, "Loyalty Points"),
+        &String::from_str(env, "PTS"),
+    );
+    points.set_minter(&client.address, &true);
+    points
+}
+
+#[test]
+fn test_points_rewards() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+
+    let points = create_points_token(&env, &client, &admin);
+    let points_id = points.address.clone();
+
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        1,
+        4,
+        vec![&env, points_id.clone()],
+        vec![&env, 1000, 100, 10],
+    )
+    .unwrap()
+    .unwrap();
+    create_utility(&env, &client, &admin, &admin);
+
+    assert_error(
+        client.try_set_points_award(&1, &admin, &5, &20),
+        UtilityError::InvalidReceiptType,
+    );
+    assert_error(
+        client.try_set_points_award(&0, &admin, &-1, &20),
+        UtilityError::InvalidConfig,
+    );
+    client.set_points_award(&0, &admin, &5, &20);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("points"),
+                    symbol_short!("set"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                PointsAwardSet {
+                    per_redemption: 5,
+                    per_entry: 20
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(
+        client.get_points_award(&0),
+        Some(PointsAward {
+            per_redemption: 5,
+            per_entry: 20,
+        })
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.join_raffle(&0, &user);
+    assert_eq!(points.balance(&user), 20);
+
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    let all = env.events().all();
+    assert_eq!(
+        all.slice(all.len() - 2..all.len() - 1),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("points"),
+                    symbol_short!("awarded"),
+                    EVENT_VERSION,
+                    0_u64
+                )
+                    .into_val(&env),
+                PointsAwarded {
+                    user: user.clone(),
+                    token: points_id.clone(),
+                    amount: 5,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(points.balance(&user), 25);
+
+    env.ledger().with_mut(|l| l.timestamp = 2000);
+    client.end_raffle(&0, &admin);
+    client.claim_reward(&0, &user, &admin);
+    assert_eq!(points.balance(&user), 125);
+
+    client.set_points_award(&0, &admin, &0, &0);
+    assert_eq!(client.get_points_award(&0), None);
+}
+
+#[test]
+fn test_points_catalog() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    let points = create_points_token(&env, &client, &admin);
+    let payout = create_payment_token(&env, &[&admin]);
+    let payout_balance = |a: &Address| token::TokenClient::new(&env, &payout).balance(a);
+    points.mint(&admin, &user, &100);
+    create_utility(&env, &client, &admin, &admin);
+
+    assert_error(
+        client.try_add_catalog_item(
+            &admin,
+            &points.address,
+            &0,
+            &2,
+            &CatalogReward::Token(payout.clone(), 50),
+        ),
+        RewardError::InvalidCatalogItem,
+    );
+    assert_error(
+        client.try_add_catalog_item(
+            &other,
+            &points.address,
+            &10,
+            &1,
+            &CatalogReward::Usages(0, 5),
+        ),
+        UtilityError::NotAuthorized,
+    );
+
+    client.add_catalog_item(
+        &admin,
+        &points.address,
+        &30,
+        &2,
+        &CatalogReward::Token(payout.clone(), 50),
+    );
+    client.add_catalog_item(
+        &admin,
+        &points.address,
+        &10,
+        &1,
+        &CatalogReward::Usages(0, 5),
+    );
+    assert_eq!(payout_balance(&client.address), 100);
+
+    client.redeem_catalog_item(&0, &user, &None);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("catalog"),
+                    symbol_short!("redeemed"),
+                    EVENT_VERSION,
+                    0_u64
+                )
+                    .into_val(&env),
+                CatalogItemRedeemed {
+                    user: user.clone(),
+                    price: 30,
+                    remaining: 1,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(points.balance(&user), 70);
+    assert_eq!(payout_balance(&user), 50);
+
+    client.set_catalog_price(&0, &admin, &40);
+    assert_eq!(client.get_catalog_item(&0).price, 40);
+    client.set_catalog_stock(&0, &admin, &0);
+    assert_eq!(payout_balance(&admin), 950);
+    assert_error(
+        client.try_redeem_catalog_item(&0, &user, &None),
+        RewardError::OutOfStock,
+    );
+
+    assert_error(
+        client.try_redeem_catalog_item(&1, &user, &None),
+        UtilityError::MissingParameter,
+    );
+    assert_error(
+        client.try_redeem_catalog_item(&1, &user, &Some(nft.clone())),
+        UtilityError::UtilityNotClaimed,
+    );
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.redeem_catalog_item(&1, &user, &Some(nft.clone()));
+    assert_eq!(client.get_token_utility(&nft, &0).usage, 15);
+    assert_eq!(points.balance(&user), 60);
+
+    let page = client.list_catalog_items(&0, &1);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next_cursor, Some(1));
+    assert_eq!(
+        client.list_catalog_purchases(&0, &0, &10),
+        vec![
+            &env,
+            CatalogPurchase {
+                user: user.clone(),
+                price: 30,
+                timestamp: 0,
+            },
+        ]
+    );
+    assert_error(client.try_get_catalog_item(&2), RewardError::ItemNotFound);
+}
+
+#[test]
+fn test_coupon_codes() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    create_utility(&env, &client, &admin, &admin);
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(client.get_coupon_codes(&0, &user), vec![&env]);
+
+    let first = Bytes::from_array(&env, b"SAVE10-A");
+    let second = Bytes::from_array(&env, b"SAVE10-B");
+    let first: BytesN<32> = env.crypto().sha256(&first).into();
+    let second: BytesN<32> = env.crypto().sha256(&second).into();
+    assert_error(
+        client.try_add_coupon_codes(&0, &other, &vec![&env, first.clone()]),
+        UtilityError::NotAuthorized,
+    );
+    assert_eq!(
+        client.add_coupon_codes(&0, &admin, &vec![&env, first.clone(), second.clone()]),
+        CouponInventory {
+            total: 2,
+            assigned: 0,
+        }
+    );
+
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(
+        client.get_coupon_inventory(&0),
+        CouponInventory {
+            total: 2,
+            assigned: 2,
+        }
+    );
+    assert_eq!(
+        client.get_coupon_codes(&0, &user),
+        vec![&env, first, second]
+    );
+    assert_eq!(client.get_coupon_codes(&0, &other), vec![&env]);
+    assert!(client.verify_coupon_code(&0, &user, &Bytes::from_array(&env, b"SAVE10-B")));
+    assert!(!client.verify_coupon_code(&0, &other, &Bytes::from_array(&env, b"SAVE10-B")));
+    assert!(!client.verify_coupon_code(&0, &user, &Bytes::from_array(&env, b"SAVE10-C")));
+
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        RewardError::OutOfStock,
+    );
+    assert_eq!(client.get_token_utility(&nft, &0).usage, 7);
+}
+
+#[test]
+fn test_claim_with_code() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let reward_token = create_minted_reward(&env, &client, &admin);
+    let code = Bytes::from_array(&env, b"VIP-2026");
+    let code_hash: BytesN<32> = env.crypto().sha256(&code).into();
+
+    let hashes = vec![&env, code_hash.clone()];
+    assert_eq!(client.add_claim_codes(&0, &admin, &hashes), 1);
+    assert_eq!(client.add_claim_codes(&0, &admin, &hashes), 1);
+    assert!(client.is_claim_code_valid(&0, &code_hash));
+
+    assert_error(
+        client.try_claim_with_code(&0, &Bytes::from_array(&env, b"GUESS"), &user),
+        RewardError::InvalidClaimCode,
+    );
+    assert_error(
+        client.try_claim_with_code(&0, &code, &user),
+        RewardError::ClaimCodeNotCommitted,
+    );
+
+    let commitment = claim_codes::commitment(&env, &code, &user);
+    client.commit_claim_code(&0, &user, &commitment);
+    client.commit_claim_code(&0, &attacker, &commitment);
+    assert_error(
+        client.try_claim_with_code(&0, &code, &user),
+        RewardError::ClaimCodeNotCommitted,
+    );
+
+    env.ledger().with_mut(|l| l.sequence_number += 1);
+    assert_error(
+        client.try_claim_with_code(&0, &code, &attacker),
+        RewardError::ClaimCodeNotCommitted,
+    );
+
+    // On a giveaway, code claims share the rate limit and winner count.
+    client.set_utility_type(&0, &admin, &UtilityType::Giveaway);
+    client.set_claim_rate_limit(&0, &admin, &60, &1);
+    client.claim_reward(&0, &attacker, &attacker);
+    assert_error(
+        client.try_claim_with_code(&0, &code, &user),
+        UtilityError::RateLimited,
+    );
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    client.claim_with_code(&0, &code, &user);
+    assert_eq!(client.get_giveaway_claims(&0), 2);
+    assert_eq!(
+        token::TokenClient::new(&env, &reward_token).balance(&user),
+        100
+    );
+    assert!(!client.is_claim_code_valid(&0, &code_hash));
+    assert_error(
+        client.try_claim_reward(&0, &user, &admin),
+        UtilityError::AlreadyClaimed,
+    );
+
+    client.commit_claim_code(
+        &0,
+        &attacker,
+        &claim_codes::commitment(&env, &code, &attacker),
+    );
+    env.ledger().with_mut(|l| l.sequence_number += 1);
+    assert_error(
+        client.try_claim_with_code(&0, &code, &attacker),
+        RewardError::ClaimCodeUsed,
+    );
+}
+
+#[test]
+fn test_receipt_nfts() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    create_utility(&env, &client, &admin, &admin);
+    create_utility(&env, &client, &admin, &admin);
+
+    let receipt_id = env.register_contract(None, receipt_nft::ReceiptNft);
+    let receipts = receipt_nft::ReceiptNftClient::new(&env, &receipt_id);
+    receipts.initialize(
+        &admin,
+        &String::from_str(&env, "Redemption Receipt"),
+        &String::from_str(&env, "RCPT"),
+    );
+    receipts.set_minter(&client.address, &true);
+
+    client.set_receipt_nft(&0, &admin, &Some(receipt_id.clone()));
+    assert_eq!(client.get_receipt_nft(&0), Some(receipt_id.clone()));
+    client.set_eligibility_rule(
+        &1,
+        &admin,
+        &Some(EligibilityRule::HasReceipt(receipt_id.clone(), 0)),
+    );
+    assert!(!client.evaluate_eligibility(&1, &user));
+
+    env.ledger().with_mut(|l| l.timestamp = 600);
+    client.claim_utitlity_on_nft(&nft, &0, &user, &admin);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+
+    let all = env.events().all();
+    assert_eq!(
+        all.slice(all.len() - 2..all.len() - 1),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("receipt"),
+                    symbol_short!("issued"),
+                    EVENT_VERSION,
+                    0_u64
+                )
+                    .into_val(&env),
+                ReceiptIssued {
+                    user: user.clone(),
+                    receipt_nft: receipt_id.clone(),
+                    token_id: 0,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(receipts.tokens_of(&user, &0, &10), vec![&env, 0]);
+    assert_eq!(
+        receipts.metadata(&0),
+        receipt_nft::ReceiptMetadata {
+            owner: user.clone(),
+            issuer: client.address.clone(),
+            utility_id: 0,
+            redeemed_at: 600,
+        }
+    );
+    assert!(client.evaluate_eligibility(&1, &user));
+
+    client.set_receipt_nft(&0, &admin, &None);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("receipt"),
+                    symbol_short!("set"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                ReceiptNftSet { receipt_nft: None }.into_val(&env),
+            ),
+        ]
+    );
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(receipts.balance(&user), 1);
+}
+
+#[test]
+fn test_platform_fees() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let provider = Address::generate(&env);
+    let partner = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let payment = create_payment_token(&env, &[&provider, &alice]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &payment).balance(who);
+
+    client.set_config(&admin, &ConfigUpdate::FeeToken(payment.clone()));
+    client.set_config(&admin, &ConfigUpdate::CreationFee(25));
+    client.set_config(&admin, &ConfigUpdate::PlatformFeeBps(1000));
+    create_utility(&env, &client, &provider, &partner);
+    assert_eq!(balance(&provider), 975);
+    assert_eq!(client.get_fee_balance(&payment), 25);
+
+    assert_error(
+        client.try_set_partner_share(&0, &provider, &10_001),
+        UtilityError::InvalidConfig,
+    );
+    client.set_partner_share(&0, &provider, &5000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("partner"),
+                    symbol_short!("share"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                PartnerShareSet { bps: 5000 }.into_val(&env),
+            ),
+        ]
+    );
+    client.set_ticket_sale(&0, &provider, &payment, &100, &1, &0);
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.join_raffle(&0, &alice);
+
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &provider);
+    assert_eq!(balance(&partner), 45);
+    assert_eq!(balance(&provider), 1020);
+    assert_eq!(client.get_fee_balance(&payment), 35);
+
+    assert_error(
+        client.try_withdraw_fees(&provider, &payment, &provider, &35),
+        UtilityError::NotAuthorized,
+    );
+    assert_error(
+        client.try_withdraw_fees(&admin, &payment, &admin, &36),
+        RewardError::InsufficientFees,
+    );
+    client.withdraw_fees(&admin, &payment, &admin, &35);
+    assert_eq!(balance(&admin), 35);
+    assert_eq!(client.get_fee_balance(&payment), 0);
+}
+
+#[test]
+fn test_escrowed_reward_fee() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let reward_token = create_payment_token(&env, &[&admin]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &reward_token).balance(who);
+    token::TokenClient::new(&env, &reward_token).approve(&admin, &admin, &300, &1000);
+    client.set_config(&admin, &ConfigUpdate::PlatformFeeBps(1000));
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        2,
+        vec![&env, reward_token.clone()],
+        vec![&env, 300, 100, 3],
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(balance(&client.address), 300);
+
+    client.claim_reward(&0, &user, &admin);
+    assert_eq!(balance(&user), 90);
+    assert_eq!(client.get_fee_balance(&reward_token), 10);
+    assert_eq!(balance(&client.address), 210);
+    assert_eq!(client.get_utility(&0).reward.total_amount, 200);
+}
+
+#[test]
+fn test_reclaim_reward_escrow() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let user = Address::generate(&env);
+    let reward_token = create_payment_token(&env, &[&admin]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &reward_token).balance(who);
+    token::TokenClient::new(&env, &reward_token).approve(&admin, &admin, &600, &1000);
+    for _ in 0..2 {
+        try_create(
+            &env,
+            &client,
+            &admin,
+            vec![&env, 1, 1000, 2000],
+            vec![&env, 1, 10],
+            500,
+            0,
+            2,
+            vec![&env, reward_token.clone()],
+            vec![&env, 300, 100, 3],
+        )
+        .unwrap()
+        .unwrap();
+    }
+    client.set_vesting(&1, &admin, &Vesting::Cliff(1000));
+    client.claim_reward(&0, &user, &admin);
+    client.claim_reward(&1, &user, &admin);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    assert_error(
+        client.try_reclaim_reward_escrow(&0, &admin),
+        UtilityError::ClaimWindowOpen,
+    );
+
+    // A cancelled utility keeps what its vesting grants still owe.
+    client.cancel_utility(&1, &admin);
+    assert_error(
+        client.try_reclaim_reward_escrow(&1, &user),
+        UtilityError::NotAuthorized,
+    );
+    assert_eq!(client.reclaim_reward_escrow(&1, &admin), 200);
+    assert_eq!(client.get_reward_escrow(&1), 100);
+    assert_eq!(
+        client.clawback_unvested(&1, &admin, &vec![&env, user.clone()]),
+        100
+    );
+    assert_eq!(client.get_reward_escrow(&1), 0);
+
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    assert_eq!(client.reclaim_reward_escrow(&0, &admin), 200);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("reward"),
+                    symbol_short!("reclaimed"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RewardReclaimed {
+                    token: reward_token.clone(),
+                    amount: 200,
+                    to: admin.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_error(
+        client.try_reclaim_reward_escrow(&0, &admin),
+        RewardError::NothingToWithdraw,
+    );
+    assert_error(
+        client.try_claim_reward(&0, &Address::generate(&env), &admin),
+        UtilityError::AllRewardsClaimed,
+    );
+    assert_eq!(balance(&admin), 1000 - 600 + 200 + 100 + 200);
+    assert_eq!(balance(&client.address), 0);
+}
+
+#[test]
+fn test_ticket_sale_with_escrowed_reward() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let payment = create_payment_token(&env, &[&admin, &alice, &bob]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &payment).balance(who);
+    token::TokenClient::new(&env, &payment).approve(&admin, &admin, &300, &1000);
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        1,
+        2,
+        vec![&env, payment.clone()],
+        vec![&env, 300, 100, 2],
+    )
+    .unwrap()
+    .unwrap();
+    client.set_ticket_sale(&0, &admin, &payment, &10, &3, &1);
+
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    client.buy_tickets(&0, &alice, &1);
+    client.buy_tickets(&0, &bob, &1);
+    assert_eq!(client.get_escrow_balance(&0), 20);
+    assert_eq!(client.get_reward_escrow(&0), 300);
+
+    // Settling the ticket proceeds leaves the reward pool alone.
+    env.ledger().with_mut(|l| l.timestamp = 2001);
+    client.end_raffle(&0, &admin);
+    assert_eq!(client.get_escrow_balance(&0), 0);
+    assert_eq!(balance(&admin), 1000 - 300 + 20);
+    assert_eq!(client.get_reward_escrow(&0), 300);
+
+    client.claim_reward(&0, &alice, &admin);
+    client.claim_reward(&0, &bob, &admin);
+    assert_eq!(balance(&alice), 1000 - 10 + 100);
+    assert_eq!(balance(&bob), 1000 - 10 + 100);
+    assert_eq!(client.get_reward_escrow(&0), 100);
+    assert_eq!(balance(&client.address), 100);
+}
+
+#[test]
+fn test_partner_settlement() {
+    let env = Env::default();
+    let (client, provider) = setup(&env);
+    let partner = Address::generate(&env);
+    let user = Address::generate(&env);
+    let nft = create_nft(&env, &user);
+    let payment = create_payment_token(&env, &[&provider]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &payment).balance(who);
+    create_utility(&env, &client, &provider, &partner);
+    client.claim_utitlity_on_nft(&nft, &0, &user, &provider);
+
+    assert_error(client.try_settlement_report(&0), RewardError::NoSettlement);
+    assert_error(
+        client.try_set_settlement(&0, &provider, &payment, &0),
+        UtilityError::InvalidConfig,
+    );
+    client.set_settlement(&0, &provider, &payment, &30);
+    client.fund_settlement(&0, &provider, &70);
+    assert_error(
+        client.try_withdraw_settlement(&0, &provider, &71),
+        RewardError::InsufficientEscrow,
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 600);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(balance(&partner), 30);
+    assert!(!client.get_utility(&0).paused);
+
+    // The second redemption leaves 10, too little for a third.
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(balance(&partner), 60);
+    assert!(client.get_utility(&0).paused);
+    assert_eq!(
+        client.settlement_report(&0),
+        Settlement {
+            token: payment.clone(),
+            amount_per_redemption: 30,
+            balance: 10,
+            redemptions: 2,
+            total_paid: 60,
+            low_balance: true,
+        }
+    );
+    assert_error(
+        client.try_redeem_utility_on_nft(&nft, &0, &user),
+        UtilityError::UtilityPaused,
+    );
+
+    client.fund_settlement(&0, &provider, &20);
+    assert!(!client.get_utility(&0).paused);
+    client.redeem_utility_on_nft(&nft, &0, &user);
+    assert_eq!(balance(&partner), 90);
+    assert!(client.get_utility(&0).paused);
+
+    // Withdrawing what is left of the escrow pauses the utility again.
+    client.set_settlement(&0, &provider, &payment, &10);
+    client.fund_settlement(&0, &provider, &10);
+    assert!(!client.get_utility(&0).paused);
+    client.withdraw_settlement(&0, &provider, &10);
+    assert!(client.get_utility(&0).paused);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("settle"),
+                    symbol_short!("withdrawn"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                SettlementWithdrawn {
+                    to: provider.clone(),
+                    amount: 10,
+                    balance: 0,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(balance(&provider), 1000 - 70 - 20 - 10 + 10);
+    assert_eq!(client.settlement_report(&0).total_paid, 90);
+
+    // Raising the amount past the escrow pauses it as well.
+    client.fund_settlement(&0, &provider, &10);
+    assert!(!client.get_utility(&0).paused);
+    client.set_settlement(&0, &provider, &payment, &20);
+    assert!(client.get_utility(&0).paused);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("settle"),
+                    symbol_short!("updated"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                SettlementUpdated {
+                    token: payment.clone(),
+                    amount_per_redemption: 20,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert!(client.settlement_report(&0).low_balance);
+}
+
+#[contract]
+struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, price: i128, timestamp: u64) {
+        env.storage()
+            .instance()
+            .set(&symbol_short!("PRICE"), &PriceData { price, timestamp });
+    }
+
+    pub fn lastprice(env: Env, _asset: Asset) -> Option<PriceData> {
+        env.storage().instance().get(&symbol_short!("PRICE"))
+    }
+}
+
+#[test]
+fn test_oracle_priced_reward() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = env
+        .register_stellar_asset_contract_v2(client.address.clone())
+        .address();
+    // 100 tokens of 7 decimals, 1 token per win without pricing.
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        1,
+        vec![&env, reward_token.clone()],
+        vec![&env, 1_000_000_000, 10_000_000, 100],
+    )
+    .unwrap()
+    .unwrap();
+    create_utility(&env, &client, &admin, &admin);
+    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle = MockOracleClient::new(&env, &oracle_id);
+    let balance = |who: &Address| token::TokenClient::new(&env, &reward_token).balance(who);
+    let users: [Address; 4] = core::array::from_fn(|_| Address::generate(&env));
+    // Each win is worth 10 reference units at the oracle's 7 decimals.
+    let reference = 100_000_000;
+
+    assert_error(
+        client.try_set_reward_pricing(&0, &admin, &oracle_id, &reference, &60, &2000, &3_000_000),
+        RewardError::StalePrice,
+    );
+    oracle.set_price(&0, &0);
+    assert_error(
+        client.try_set_reward_pricing(&0, &admin, &oracle_id, &reference, &60, &2000, &3_000_000),
+        RewardError::InvalidPrice,
+    );
+    // One token is quoted at 100 reference units.
+    oracle.set_price(&1_000_000_000, &0);
+    assert_error(
+        client.try_set_reward_pricing(&1, &admin, &oracle_id, &reference, &60, &2000, &3_000_000),
+        UtilityError::InvalidReceiptType,
+    );
+    assert_error(
+        client.try_set_reward_pricing(&0, &admin, &oracle_id, &reference, &60, &2000, &0),
+        UtilityError::InvalidConfig,
+    );
+    assert_error(
+        client.try_set_reward_pricing(&0, &admin, &oracle_id, &0, &60, &2000, &3_000_000),
+        UtilityError::InvalidConfig,
+    );
+    client.set_reward_pricing(&0, &admin, &oracle_id, &reference, &60, &2000, &3_000_000);
+
+    // 10 reference units buy a tenth of a token.
+    client.claim_reward(&0, &users[0], &admin);
+    assert_eq!(balance(&users[0]), 1_000_000);
+
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    oracle.set_price(&900_000_000, &100);
+    client.claim_reward(&0, &users[1], &admin);
+    assert_eq!(balance(&users[1]), 1_111_111);
+    assert_eq!(
+        client.get_reward_pricing(&0).unwrap().last_price,
+        900_000_000
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 200);
+    assert_error(
+        client.try_claim_reward(&0, &users[2], &admin),
+        RewardError::StalePrice,
+    );
+    oracle.set_price(&500_000_000, &200);
+    assert_error(
+        client.try_claim_reward(&0, &users[2], &admin),
+        RewardError::PriceDeviation,
+    );
+
+    // Re-pricing accepts the new level; the payout cap holds the claim to
+    // 0.15 tokens rather than 0.2.
+    client.set_reward_pricing(&0, &admin, &oracle_id, &reference, &60, &2000, &1_500_000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("reward"),
+                    symbol_short!("pricing"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RewardPricingSet {
+                    oracle: Some(oracle_id.clone()),
+                    reference_amount: reference,
+                    max_payout: 1_500_000,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    client.claim_reward(&0, &users[2], &admin);
+    assert_eq!(balance(&users[2]), 1_500_000);
+
+    client.remove_reward_pricing(&0, &admin);
+    assert_eq!(client.get_reward_pricing(&0), None);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    symbol_short!("reward"),
+                    symbol_short!("pricing"),
+                    EVENT_VERSION,
+                    0u64
+                )
+                    .into_val(&env),
+                RewardPricingSet {
+                    oracle: None,
+                    reference_amount: 0,
+                    max_payout: 0,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    client.claim_reward(&0, &users[3], &admin);
+    assert_eq!(balance(&users[3]), 10_000_000);
+    assert_eq!(
+        client.get_utility(&0).reward.total_amount,
+        1_000_000_000 - 1_000_000 - 1_111_111 - 1_500_000 - 10_000_000
+    );
+}
+
+#[test]
+fn test_oracle_priced_escrow_cap() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let reward_token = create_payment_token(&env, &[&admin]);
+    let balance = |who: &Address| token::TokenClient::new(&env, &reward_token).balance(who);
+    let users: [Address; 3] = core::array::from_fn(|_| Address::generate(&env));
+    token::TokenClient::new(&env, &reward_token).approve(&admin, &admin, &150, &1000);
+    try_create(
+        &env,
+        &client,
+        &admin,
+        vec![&env, 1, 1000, 2000],
+        vec![&env, 1, 10],
+        500,
+        0,
+        2,
+        vec![&env, reward_token.clone()],
+        vec![&env, 150, 50, 3],
+    )
+    .unwrap()
+    .unwrap();
+    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle = MockOracleClient::new(&env, &oracle_id);
+
+    // 10 reference units buy 100 base units.
+    oracle.set_price(&10_000_000_000_000, &0);
+    client.set_reward_pricing(&0, &admin, &oracle_id, &100_000_000, &60, &2000, &1000);
+    client.claim_reward(&0, &users[0], &admin);
+    assert_eq!(balance(&users[0]), 100);
+
+    // Only 50 is left in escrow, so that is all the next winner gets.
+    client.claim_reward(&0, &users[1], &admin);
+    assert_eq!(balance(&users[1]), 50);
+    assert_eq!(client.get_reward_escrow(&0), 0);
+    assert_error(
+        client.try_claim_reward(&0, &users[2], &admin),
+        UtilityError::AllRewardsClaimed,
+    );
+    assert_eq!(balance(&client.address), 0);
+}
@@ -0,0 +1,267 @@
+//! Paid raffle tickets.
+//!
+//! A raffle with a `TicketSale` charges `price` of the sale token per
+//! ticket and weights entries by tickets held. Payments sit in the
+//! contract under a per-utility escrow until the draw, when they are split
+//! between the provider, the platform fee vault and, if the provider shares
+//! revenue, the partner.
+//! If the raffle is cancelled, or closes with fewer than `min_entrants`,
+//! every buyer can pull their payment back with `claim_refund`.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, token, Address, Env, Symbol};
+
+use crate::structs::{EntryWeight, Selection, TicketSale, Utility, UtilityError};
+use crate::{
+    config, eligibility, events, fees, holding, points, raffle, read_admin, storage,
+    UtilityContract, UtilityContractClient,
+};
+
+pub const SALE: Symbol = symbol_short!("SALE");
+pub const ESCROW: Symbol = symbol_short!("ESCROW");
+pub const PAID: Symbol = symbol_short!("PAID");
+
+pub fn load_sale(env: &Env, utility_id: u64) -> Option<TicketSale> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), TicketSale>(&(SALE, utility_id))
+}
+
+pub fn escrow(env: &Env, utility_id: u64) -> i128 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), i128>(&(ESCROW, utility_id))
+        .unwrap_or(0)
+}
+
+fn set_escrow(env: &Env, utility_id: u64, amount: i128) {
+    let key = (ESCROW, utility_id);
+    env.storage().persistent().set(&key, &amount);
+    config::extend_persistent(env, &key);
+}
+
+pub fn paid(env: &Env, utility_id: u64, user: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, Address), i128>(&(PAID, utility_id, user.clone()))
+        .unwrap_or(0)
+}
+
+/// Charges `user` for `count` tickets and credits them to their balance.
+pub fn purchase(env: &Env, u: &Utility, sale: &TicketSale, user: &Address, count: u64) {
+    if count == 0 {
+        panic_with_error!(env, UtilityError::NoEntryWeight)
+    }
+    let held = raffle::tickets(env, u.id, user);
+    if held.saturating_add(count) > sale.max_per_user {
+        panic_with_error!(env, UtilityError::TicketCapExceeded)
+    }
+
+    let amount = sale
+        .price
+        .checked_mul(count as i128)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    token::TokenClient::new(env, &sale.token).transfer(
+        user,
+        &env.current_contract_address(),
+        &amount,
+    );
+
+    let escrowed = escrow(env, u.id)
+        .checked_add(amount)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    set_escrow(env, u.id, escrowed);
+
+    let paid_key = (PAID, u.id, user.clone());
+    env.storage()
+        .persistent()
+        .set(&paid_key, &(paid(env, u.id, user) + amount));
+    config::extend_persistent(env, &paid_key);
+
+    raffle::add_tickets(env, u.id, user, count);
+    events::tickets_bought(env, u.id, user.clone(), count, amount);
+}
+
+/// Whether the raffle closes without enough entrants to be drawn.
+pub fn below_minimum(env: &Env, u: &Utility) -> bool {
+    load_sale(env, u.id).is_some_and(|sale| raffle::entrant_count(env, u.id) < sale.min_entrants)
+}
+
+/// Pays the escrowed ticket proceeds out to the provider, the partner and
+/// the fee vault.
+pub fn settle(env: &Env, u: &Utility) {
+    let Some(sale) = load_sale(env, u.id) else {
+        return;
+    };
+    let total = escrow(env, u.id);
+    if total == 0 {
+        return;
+    }
+
+    let platform_amount = fees::platform_fee(env, total);
+    let revenue = total - platform_amount;
+    let partner_amount = fees::bps_of(env, revenue, fees::partner_share(env, u.id));
+    let provider_amount = revenue - partner_amount;
+
+    let client = token::TokenClient::new(env, &sale.token);
+    let contract = env.current_contract_address();
+    if provider_amount > 0 {
+        client.transfer(&contract, &u.provider, &provider_amount);
+    }
+    if partner_amount > 0 {
+        client.transfer(&contract, &u.partner, &partner_amount);
+        events::partner_paid(
+            env,
+            u.id,
+            u.partner.clone(),
+            sale.token.clone(),
+            partner_amount,
+        );
+    }
+    fees::accrue(env, u.id, &sale.token, platform_amount);
+    set_escrow(env, u.id, 0);
+
+    events::proceeds_settled(env, u.id, sale.token, provider_amount, platform_amount);
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Puts a raffle's tickets on sale at `price` of `token_address` each,
+    /// at most `max_per_user` per entrant. Entries become ticket weighted.
+    pub fn set_ticket_sale(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        token_address: Address,
+        price: i128,
+        max_per_user: u64,
+        min_entrants: u32,
+    ) -> TicketSale {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.selection != Selection::Raffle || u.raffle.ended {
+            panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
+        }
+        if raffle::entrant_count(&env, utility_id) > 0 {
+            panic_with_error!(&env, UtilityError::RaffleHasEntrants)
+        }
+        if price <= 0 || max_per_user == 0 {
+            panic_with_error!(&env, UtilityError::InvalidConfig)
+        }
+
+        let sale = TicketSale {
+            token: token_address,
+            price,
+            max_per_user,
+            min_entrants,
+        };
+        let key = (SALE, utility_id);
+        env.storage().persistent().set(&key, &sale);
+        config::extend_persistent(&env, &key);
+
+        u.raffle.weight = EntryWeight::Tickets;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        sale
+    }
+
+    pub fn get_ticket_sale(env: Env, utility_id: u64) -> Option<TicketSale> {
+        load_sale(&env, utility_id)
+    }
+
+    /// Buys `count` tickets, entering the raffle on the first purchase and
+    /// adding weight to the existing entry afterwards.
+    pub fn buy_tickets(env: Env, utility_id: u64, user: Address, count: u64) {
+        user.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+        let sale = load_sale(&env, utility_id)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::NoTicketSale));
+
+        raffle::require_open(&env, &u);
+
+        let entered = raffle::has_entered(&env, utility_id, &user);
+        if !entered {
+            holding::require_held(&env, &u, None, &user);
+            eligibility::require_eligible(&env, utility_id, &user);
+        }
+
+        purchase(&env, &u, &sale, &user, count);
+
+        if entered {
+            raffle::add_weight(&env, utility_id, &user, count);
+        } else {
+            raffle::enter(&env, &u, &user);
+            points::award_entry(&env, &u, &user);
+            events::raffle_joined(&env, utility_id, user);
+        }
+    }
+
+    /// Cancels a raffle before its draw. Ticket buyers can then reclaim
+    /// their payments with `claim_refund`.
+    pub fn cancel_raffle(env: Env, utility_id: u64, sender: Address) {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender && read_admin(&env) != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.selection != Selection::Raffle {
+            panic_with_error!(&env, UtilityError::InvalidRaffleSelection)
+        }
+        if u.raffle.ended {
+            panic_with_error!(&env, UtilityError::RaffleAlreadyEnded)
+        }
+
+        u.raffle.ended = true;
+        u.raffle.cancelled = true;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::raffle_cancelled(&env, utility_id, raffle::entrant_count(&env, utility_id));
+    }
+
+    /// Returns everything `user` paid for tickets to a cancelled raffle.
+    pub fn claim_refund(env: Env, utility_id: u64, user: Address) -> i128 {
+        user.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        if !u.raffle.cancelled {
+            panic_with_error!(&env, UtilityError::RefundUnavailable)
+        }
+        let amount = paid(&env, utility_id, &user);
+        if amount == 0 {
+            panic_with_error!(&env, UtilityError::NothingToRefund)
+        }
+        let sale = load_sale(&env, utility_id)
+            .unwrap_or_else(|| panic_with_error!(&env, UtilityError::NoTicketSale));
+
+        env.storage()
+            .persistent()
+            .remove(&(PAID, utility_id, user.clone()));
+        set_escrow(&env, utility_id, escrow(&env, utility_id) - amount);
+        token::TokenClient::new(&env, &sale.token).transfer(
+            &env.current_contract_address(),
+            &user,
+            &amount,
+        );
+
+        events::refund_claimed(&env, utility_id, user, sale.token, amount);
+        amount
+    }
+
+    pub fn get_escrow_balance(env: Env, utility_id: u64) -> i128 {
+        escrow(&env, utility_id)
+    }
+
+    pub fn get_refund_due(env: Env, utility_id: u64, user: Address) -> i128 {
+        let u = Self::get_utility(env.clone(), utility_id);
+        if u.raffle.cancelled {
+            paid(&env, utility_id, &user)
+        } else {
+            0
+        }
+    }
+}
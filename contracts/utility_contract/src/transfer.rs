@@ -0,0 +1,90 @@
+//! Handling of per-NFT utility state when the NFT changes hands.
+//!
+//! `TokenUtility.owner` records who held the NFT when the utility was
+//! claimed or last carried over. A different holder is noticed either on
+//! their next redemption or through the `on_nft_transfer` hook, and the
+//! utility's `TransferPolicy` decides what they inherit.
+
+use soroban_sdk::{contractimpl, panic_with_error, Address, Env};
+
+use crate::structs::{TokenUtility, TransferPolicy, Utility, UtilityError};
+use crate::{
+    config, events, grant_utility, holding, storage, UtilityContract, UtilityContractClient,
+};
+
+/// Moves `token_utility` to `new_owner` according to the utility's policy.
+/// Returns `false` without changing anything for soulbound utilities.
+pub fn apply_policy(
+    env: &Env,
+    u: &Utility,
+    token_utility: &mut TokenUtility,
+    new_owner: &Address,
+) -> bool {
+    if token_utility.owner == *new_owner {
+        return true;
+    }
+
+    let from = token_utility.owner.clone();
+    match u.transfer_policy {
+        TransferPolicy::Soulbound => return false,
+        TransferPolicy::CarryOver => token_utility.owner = new_owner.clone(),
+        TransferPolicy::ResetOnTransfer => {
+            token_utility.owner = new_owner.clone();
+            grant_utility(env, u, token_utility);
+        }
+    }
+    storage::save_token_utility(env, token_utility);
+
+    events::utility_transferred(env, token_utility, from, u.transfer_policy);
+    true
+}
+
+#[contractimpl]
+impl UtilityContract {
+    pub fn set_transfer_policy(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        policy: TransferPolicy,
+    ) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+
+        u.transfer_policy = policy;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Transfer hook for NFT contracts. Must be invoked by the NFT contract
+    /// itself; applies each bound utility's policy for the new holder `to`.
+    pub fn on_nft_transfer(env: Env, token_address: Address, from: Address, to: Address) {
+        token_address.require_auth();
+        holding::forget_holder(&env, &token_address, &from);
+
+        let len = storage::index_len(&env, storage::BY_TOKEN_COUNT, &token_address)
+            .min(config::max_page_size(&env) as u64);
+        for position in 0..len {
+            let Some(utility_id) =
+                storage::index_get(&env, storage::BY_TOKEN, &token_address, position)
+            else {
+                continue;
+            };
+            let (Some(u), Some(mut token_utility)) = (
+                storage::load_utility(&env, utility_id),
+                storage::load_token_utility(&env, &token_address, utility_id),
+            ) else {
+                continue;
+            };
+
+            if token_utility.owner == from {
+                apply_policy(&env, &u, &mut token_utility, &to);
+            }
+        }
+    }
+}
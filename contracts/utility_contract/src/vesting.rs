@@ -0,0 +1,215 @@
+//! Vesting reward payouts.
+//!
+//! When a utility's `Reward` carries a `Vesting` schedule, `claim_reward`
+//! records a `VestingGrant` under `(VEST, utility_id, user)` instead of
+//! paying out, and the winner withdraws whatever has unlocked with
+//! `withdraw_vested`. Vesting stops at `cancelled_at` once the provider
+//! cancels the utility; the unvested remainder can then be clawed back.
+//! What grants still owe is tracked per utility under `(VEST_OUT,
+//! utility_id)` so that reclaiming an escrow leaves it in place.
+
+use soroban_sdk::{contractimpl, panic_with_error, symbol_short, Address, Env, Symbol, Vec};
+
+use crate::structs::{Receipt, RewardError, Utility, UtilityError, Vesting, VestingGrant};
+use crate::{
+    config, escrow, events, pay_reward, reward_token, storage, UtilityContract,
+    UtilityContractClient,
+};
+
+pub const VEST: Symbol = symbol_short!("VEST");
+pub const OUTSTANDING: Symbol = symbol_short!("VEST_OUT");
+
+pub fn load(env: &Env, utility_id: u64, user: &Address) -> Option<VestingGrant> {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64, Address), VestingGrant>(&(VEST, utility_id, user.clone()))
+}
+
+fn save(env: &Env, utility_id: u64, user: &Address, grant: &VestingGrant) {
+    let key = (VEST, utility_id, user.clone());
+    env.storage().persistent().set(&key, grant);
+    config::extend_persistent(env, &key);
+}
+
+/// Granted reward of `utility_id` not yet withdrawn or clawed back.
+pub fn outstanding(env: &Env, utility_id: u64) -> i128 {
+    env.storage()
+        .persistent()
+        .get::<(Symbol, u64), i128>(&(OUTSTANDING, utility_id))
+        .unwrap_or(0)
+}
+
+fn add_outstanding(env: &Env, utility_id: u64, delta: i128) {
+    let key = (OUTSTANDING, utility_id);
+    let total = outstanding(env, utility_id)
+        .checked_add(delta)
+        .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow));
+    env.storage().persistent().set(&key, &total);
+    config::extend_persistent(env, &key);
+}
+
+/// Amount of `grant` unlocked at time `at`.
+pub fn vested_at(env: &Env, grant: &VestingGrant, at: u64) -> i128 {
+    let elapsed = at.saturating_sub(grant.start);
+    match grant.vesting {
+        Vesting::None => grant.total,
+        Vesting::Cliff(delay) => {
+            if elapsed >= delay {
+                grant.total
+            } else {
+                0
+            }
+        }
+        Vesting::Linear(cliff, duration) => {
+            if elapsed < cliff {
+                0
+            } else if elapsed >= duration {
+                grant.total
+            } else {
+                grant
+                    .total
+                    .checked_mul(elapsed as i128)
+                    .unwrap_or_else(|| panic_with_error!(env, UtilityError::ArithmeticOverflow))
+                    / duration as i128
+            }
+        }
+    }
+}
+
+/// Amount of `grant` unlocked now, frozen at the cancellation time of `u`.
+fn vested_now(env: &Env, u: &Utility, grant: &VestingGrant) -> i128 {
+    let now = env.ledger().timestamp();
+    let at = if u.cancelled_at > 0 {
+        now.min(u.cancelled_at)
+    } else {
+        now
+    };
+    vested_at(env, grant, at)
+}
+
+/// Records `user`'s reward of `amount` as a grant vesting from now and
+/// returns the reward token.
+pub fn grant(env: &Env, u: &Utility, user: &Address, amount: i128) -> Address {
+    let token_address = reward_token(env, u);
+    save(
+        env,
+        u.id,
+        user,
+        &VestingGrant {
+            token: token_address.clone(),
+            total: amount,
+            withdrawn: 0,
+            start: env.ledger().timestamp(),
+            vesting: u.reward.vesting.clone(),
+        },
+    );
+    add_outstanding(env, u.id, amount);
+    token_address
+}
+
+#[contractimpl]
+impl UtilityContract {
+    /// Sets how rewards claimed from now on unlock. Existing grants keep
+    /// the schedule they were claimed under.
+    pub fn set_vesting(env: Env, utility_id: u64, sender: Address, vesting: Vesting) -> Utility {
+        sender.require_auth();
+        let mut u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if let Vesting::Linear(cliff, duration) = vesting {
+            if duration == 0 || cliff > duration {
+                panic_with_error!(&env, RewardError::InvalidVesting)
+            }
+        }
+
+        u.reward.vesting = vesting;
+        storage::save_utility(&env, utility_id, &u);
+
+        events::utility_updated(&env, utility_id, &u);
+        u
+    }
+
+    /// Pays out everything `user` has vested and not yet withdrawn.
+    pub fn withdraw_vested(env: Env, utility_id: u64, user: Address) -> i128 {
+        user.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+        let mut grant = load(&env, utility_id, &user)
+            .unwrap_or_else(|| panic_with_error!(&env, RewardError::NoVestingGrant));
+
+        let amount = vested_now(&env, &u, &grant) - grant.withdrawn;
+        if amount <= 0 {
+            panic_with_error!(&env, RewardError::NothingToWithdraw)
+        }
+
+        grant.withdrawn += amount;
+        save(&env, utility_id, &user, &grant);
+        add_outstanding(&env, utility_id, -amount);
+        pay_reward(&env, &u, &user, amount);
+
+        events::vested_withdrawn(&env, utility_id, user, grant.token, amount);
+        amount
+    }
+
+    pub fn get_vesting_grant(env: Env, utility_id: u64, user: Address) -> Option<VestingGrant> {
+        load(&env, utility_id, &user)
+    }
+
+    pub fn get_vested_amount(env: Env, utility_id: u64, user: Address) -> i128 {
+        let u = Self::get_utility(env.clone(), utility_id);
+        load(&env, utility_id, &user).map_or(0, |grant| vested_now(&env, &u, &grant))
+    }
+
+    pub fn get_withdrawn_amount(env: Env, utility_id: u64, user: Address) -> i128 {
+        load(&env, utility_id, &user).map_or(0, |grant| grant.withdrawn)
+    }
+
+    /// Cuts the grants of `users` on a cancelled utility down to what had
+    /// vested at cancellation. Held reward tokens go back to the provider;
+    /// minted rewards are simply never minted. Returns the total clawed back.
+    pub fn clawback_unvested(
+        env: Env,
+        utility_id: u64,
+        sender: Address,
+        users: Vec<Address>,
+    ) -> i128 {
+        sender.require_auth();
+        let u = Self::get_utility(env.clone(), utility_id);
+
+        if u.provider != sender {
+            panic_with_error!(&env, UtilityError::NotAuthorized)
+        }
+        if u.cancelled_at == 0 {
+            panic_with_error!(&env, UtilityError::UtilityNotCancelled)
+        }
+        if users.len() > config::max_batch_size(&env) {
+            panic_with_error!(&env, UtilityError::BatchTooLarge)
+        }
+
+        let mut clawed = 0i128;
+        for user in users.iter() {
+            let Some(mut grant) = load(&env, utility_id, &user) else {
+                continue;
+            };
+            let vested = vested_at(&env, &grant, u.cancelled_at);
+            let unvested = grant.total - vested;
+            if unvested <= 0 {
+                continue;
+            }
+
+            grant.total = vested;
+            grant.vesting = Vesting::None;
+            save(&env, utility_id, &user, &grant);
+            add_outstanding(&env, utility_id, -unvested);
+
+            if u.reward.receipt == Receipt::External {
+                escrow::release(&env, utility_id, &grant.token, &u.provider, unvested);
+            }
+            clawed += unvested;
+
+            events::vesting_clawed_back(&env, utility_id, user, grant.token, unvested);
+        }
+        clawed
+    }
+}